@@ -4,11 +4,11 @@ _tldr() {
     local cur="${COMP_WORDS[COMP_CWORD]}"
     local prev="${COMP_WORDS[COMP_CWORD-1]}"
 
-    local opts="-u -l -a -i -r -p -L -o -c -R -q -v -h \
-    --update --list --list-all --list-platforms --list-languages \
-    --info --render --clean-cache --gen-config --config-path --platform \
-    --language --offline --compact --no-compact --raw --no-raw --quiet \
-    --color --config --version --help"
+    local opts="-u -l -a -s -i -r -p -L -o -c -R -q -v -h \
+    --update --list --list-all --list-platforms --list-languages --search --list-translations \
+    --info --render --import --export --verify-cache --repair --whats-new --since --clean-cache \
+    --gen-config --config-path --platform --language --offline --no-follow-aliases --compact \
+    --no-compact --raw --no-raw --quiet --json-errors --color --config --version --help"
 
     if [[ $cur == -* ]]; then
         mapfile -t COMPREPLY < <(compgen -W "$opts" -- "$cur")
@@ -16,7 +16,7 @@ _tldr() {
     fi
 
     case $prev in
-        -r|--render|--config)
+        -r|--render|--import|--export|--config)
             mapfile -t COMPREPLY < <(compgen -f -- "$cur");;
         --color)
             mapfile -t COMPREPLY < <(compgen -W "auto always never" -- "$cur");;
@@ -14,16 +14,27 @@ complete -c tldr -s l -l list -d "List all pages in the current platform"
 complete -c tldr -s a -l list-all -d "List all pages"
 complete -c tldr -s a -l list-platforms -d "List available platforms"
 complete -c tldr -s a -l list-languages -d "List installed languages"
-complete -c tldr -s i -l info -d "Show cache information (path, age, installed languages and the number of pages)"
+complete -c tldr -s s -l search -d "Search titles, descriptions and examples of all pages" -x
+complete -c tldr -l list-translations -d "List all languages and platforms in which a page exists" -x -a \
+    "(tldr --offline --list-all 2> /dev/null)"
+complete -c tldr -s i -l info -d "Show cache information (path, age, platform, installed languages and the number of pages)"
+complete -c tldr -l import -d "Install pages from a local archive (tldr.zip, tldr-pages.<lang>.zip or a bundle)" -r
+complete -c tldr -l export -d "Pack the cache into a bundle that can be installed elsewhere using --import" -r
+complete -c tldr -l verify-cache -d "Check installed pages for missing, modified and unexpected files"
+complete -c tldr -l repair -d "Download damaged languages again (use with --verify-cache)"
+complete -c tldr -l whats-new -d "Show pages that were added, removed or changed by updates"
+complete -c tldr -l since -d "Only show changes made since this date (use with --whats-new)" -x
 complete -c tldr -l clean-cache -d "Clean the cache"
 complete -c tldr -l gen-config -d "Print the default config"
 complete -c tldr -l config-path -d "Print the default config path and create the config directory"
 complete -c tldr -s o -l offline -d "Do not update the cache, even if it is stale"
+complete -c tldr -l no-follow-aliases -d "Show alias pages instead of the pages of the commands they refer to"
 complete -c tldr -s c -l compact -d "Strip empty lines from output"
 complete -c tldr -l no-compact -d "Do not strip empty lines from output (overrides --compact)"
 complete -c tldr -s R -l raw -d "Print pages in raw markdown instead of rendering them"
 complete -c tldr -l no-raw -d "Render pages instead of printing raw file contents (overrides --raw)"
 complete -c tldr -s q -l quiet -d "Suppress status messages and warnings"
+complete -c tldr -l json-errors -d "Print errors as JSON objects (with suggestions of similar pages)"
 complete -c tldr -s v -l version -d "Print version"
 complete -c tldr -s h -l help -d "Print help"
 complete -c tldr -f -a "(tldr --offline --list-all 2> /dev/null)"
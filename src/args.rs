@@ -2,23 +2,7 @@ use std::path::PathBuf;
 
 use clap::{ArgAction, ColorChoice, Parser};
 
-const DEFAULT_PLATFORM: &str = if cfg!(target_os = "linux") {
-    "linux"
-} else if cfg!(target_os = "macos") {
-    "osx"
-} else if cfg!(target_os = "windows") {
-    "windows"
-} else if cfg!(target_os = "freebsd") {
-    "freebsd"
-} else if cfg!(target_os = "openbsd") {
-    "openbsd"
-} else if cfg!(target_os = "netbsd") {
-    "netbsd"
-} else if cfg!(target_os = "android") {
-    "android"
-} else {
-    "common"
-};
+use crate::util;
 
 const AFTER_HELP: &str = if cfg!(target_os = "windows") {
     // Man pages are not available on Windows.
@@ -27,6 +11,11 @@ const AFTER_HELP: &str = if cfg!(target_os = "windows") {
     "See 'man tldr' or https://tldr.sh/tlrc for more information."
 };
 
+/// Parse a `YYYY-MM-DD` date into seconds since the Unix epoch.
+fn parse_date(s: &str) -> Result<u64, String> {
+    util::parse_date(s).ok_or_else(|| "expected a date in the YYYY-MM-DD format".to_string())
+}
+
 #[derive(Parser)]
 #[command(
     arg_required_else_help = true,
@@ -66,7 +55,15 @@ pub struct Cli {
     #[arg(long, group = "operations")]
     pub list_languages: bool,
 
-    /// Show cache information (path, age, installed languages and the number of pages).
+    /// Search titles, descriptions and examples of all pages.
+    #[arg(short, long, group = "operations", value_name = "TERMS", num_args = 1..)]
+    pub search: Option<Vec<String>>,
+
+    /// List all languages and platforms in which a page exists.
+    #[arg(long, group = "operations", value_name = "PAGE", num_args = 1..)]
+    pub list_translations: Option<Vec<String>>,
+
+    /// Show cache information (path, age, platform, installed languages and the number of pages).
     #[arg(short, long, group = "operations")]
     pub info: bool,
 
@@ -74,6 +71,30 @@ pub struct Cli {
     #[arg(short, long, group = "operations", value_name = "FILE")]
     pub render: Option<PathBuf>,
 
+    /// Install pages from a local archive (tldr.zip, tldr-pages.<lang>.zip or a bundle).
+    #[arg(long, group = "operations", value_name = "FILE")]
+    pub import: Option<PathBuf>,
+
+    /// Pack the cache into a bundle that can be installed elsewhere using --import.
+    #[arg(long, group = "operations", value_name = "FILE")]
+    pub export: Option<PathBuf>,
+
+    /// Check installed pages for missing, modified and unexpected files.
+    #[arg(long, group = "operations")]
+    pub verify_cache: bool,
+
+    /// Restore or download damaged languages again (use with --verify-cache).
+    #[arg(long, requires = "verify_cache")]
+    pub repair: bool,
+
+    /// Show pages that were added, removed or changed by updates.
+    #[arg(long, group = "operations")]
+    pub whats_new: bool,
+
+    /// Only show changes made since this date (use with --whats-new).
+    #[arg(long, value_name = "YYYY-MM-DD", requires = "whats_new", value_parser = parse_date)]
+    pub since: Option<u64>,
+
     /// Clean the cache.
     #[arg(long, group = "operations")]
     pub clean_cache: bool,
@@ -86,9 +107,9 @@ pub struct Cli {
     #[arg(long, group = "operations")]
     pub config_path: bool,
 
-    /// Specify the platform to use (linux, osx, windows, etc.).
-    #[arg(short, long, default_value = DEFAULT_PLATFORM)]
-    pub platform: String,
+    /// Specify the platforms to use (linux, osx, windows, etc.), searched in order.
+    #[arg(short, long = "platform", value_name = "PLATFORM")]
+    pub platforms: Vec<String>,
 
     /// Specify the languages to use.
     #[arg(short = 'L', long = "language", value_name = "LANGUAGE_CODE")]
@@ -98,6 +119,10 @@ pub struct Cli {
     #[arg(short, long)]
     pub offline: bool,
 
+    /// Show alias pages instead of the pages of the commands they refer to.
+    #[arg(long)]
+    pub no_follow_aliases: bool,
+
     /// Strip empty lines from output.
     #[arg(short, long)]
     pub compact: bool,
@@ -118,6 +143,10 @@ pub struct Cli {
     #[arg(short, long)]
     pub quiet: bool,
 
+    /// Print errors as JSON objects (with suggestions of similar pages).
+    #[arg(long)]
+    pub json_errors: bool,
+
     /// Specify when to enable color.
     #[arg(long, value_name = "WHEN", default_value_t = ColorChoice::default())]
     pub color: ColorChoice,
@@ -1,28 +1,302 @@
-use std::collections::{BTreeMap, HashMap};
-use std::ffi::OsString;
-use std::fs::{self, File};
-use std::io::{self, BufWriter, Cursor, Read, Write};
-use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::{OsStr, OsString};
+use std::fmt::{self, Display, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
 use once_cell::unsync::OnceCell;
-use yansi::Color::{Green, Red};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use yansi::Color::{Cyan, Green, Red, Yellow};
 use yansi::Paint;
-use zip::ZipArchive;
-
-use crate::config::Config;
-use crate::error::{Error, Result};
-use crate::util::{self, info_end, info_start, infoln, warnln, Dedup};
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::changelog::{ChangeKind, Changelog, PageSums};
+use crate::config::{CacheConfig, Config, StorageMode};
+use crate::error::{Error, ErrorKind, Result};
+use crate::index::{Index, IndexBuilder};
+use crate::lock::CacheLock;
+use crate::mirror::{Mirror, Validators};
+use crate::search::Hit;
+use crate::store::{DirStore, PageStore, ZipStore};
+use crate::util::{self, infoln, warnln, Dedup, DetectedPlatform, Sha256Writer};
 
 pub const ENGLISH_DIR: &str = "pages.en";
-const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), '/', env!("CARGO_PKG_VERSION"));
+const SUMFILE: &str = "tldr.sha256sums";
+/// HTTP headers of the checksum file, used to make conditional requests.
+const SUMFILE_HEADERS: &str = "tldr.sha256sums.headers";
+/// Detached Ed25519 signature of the checksum file.
+const SUMFILE_SIG: &str = "tldr.sha256sums.sig";
+/// Index of installed pages, rebuilt on every update.
+const INDEX: &str = "tldr.index";
+/// SHA256 sums of extracted pages, used to verify the cache.
+const PAGE_SUMS: &str = "tldr.pages.sha256sums";
+/// The maximum number of similar pages suggested when a page is not found.
+const MAX_SUGGESTIONS: usize = 5;
+/// Suffix of patches that append examples to pages.
+const PATCH_SUFFIX: &str = ".patch.md";
+/// Pages added, removed or changed by updates.
+const CHANGELOG: &str = "tldr.changes";
+/// Prefix of staging directories, which are created next to language directories during updates.
+const STAGING_PREFIX: &str = ".staging.";
+/// Manifest of bundles created by `Cache::export`.
+const BUNDLE_MANIFEST: &str = "manifest.toml";
+/// Mask of the file type bits in Unix modes stored in archives.
+const S_IFMT: u32 = 0o170_000;
+const S_IFREG: u32 = 0o100_000;
+const S_IFDIR: u32 = 0o040_000;
+/// How long to wait for other processes to finish modifying the cache.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+type PagesArchive = ZipArchive<BufReader<File>>;
+
+/// HTTP headers of the last downloaded checksum file.
+#[derive(Default)]
+struct SumfileHeaders {
+    /// Validators of every mirror that served the current checksum file, by mirror URL.
+    validators: BTreeMap<String, Validators>,
+    /// Languages that were checked against the checksum file.
+    languages: Vec<String>,
+    /// The hex-encoded key that signed the checksum file.
+    signed_by: Option<String>,
+}
+
+impl SumfileHeaders {
+    fn parse(s: &str) -> Self {
+        let mut headers = Self::default();
+
+        for l in s.lines() {
+            match l.split_once(": ") {
+                Some((k, v)) if k.starts_with("etag ") || k.starts_with("last-modified ") => {
+                    let (header, mirror) = k.split_once(' ').unwrap();
+                    let validators = headers.validators.entry(mirror.to_string()).or_default();
+                    if header == "etag" {
+                        validators.etag = Some(v.to_string());
+                    } else {
+                        validators.last_modified = Some(v.to_string());
+                    }
+                }
+                Some(("signed-by", v)) => headers.signed_by = Some(v.to_string()),
+                Some(("languages", v)) => {
+                    headers.languages = v.split_whitespace().map(str::to_string).collect();
+                }
+                _ => {}
+            }
+        }
+
+        headers
+    }
+}
+
+impl Display for SumfileHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (mirror, validators) in &self.validators {
+            if let Some(etag) = &validators.etag {
+                writeln!(f, "etag {mirror}: {etag}")?;
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                writeln!(f, "last-modified {mirror}: {last_modified}")?;
+            }
+        }
+        if let Some(signed_by) = &self.signed_by {
+            writeln!(f, "signed-by: {signed_by}")?;
+        }
+        writeln!(f, "languages: {}", self.languages.join(" "))
+    }
+}
+
+/// Metadata stored in bundles created by `Cache::export`.
+#[derive(Serialize, Deserialize)]
+struct BundleManifest {
+    /// Version of tlrc that created the bundle.
+    version: String,
+    /// Creation time in seconds since the Unix epoch.
+    created: u64,
+    /// Languages in the bundle.
+    languages: Vec<String>,
+}
+
+impl BundleManifest {
+    /// Read the manifest and the checksum file from `archive`.
+    /// Returns `None` if the archive is not a bundle.
+    fn read(
+        archive: &mut PagesArchive,
+        limits: &mut ExtractLimits,
+    ) -> Result<Option<(Self, String)>> {
+        let mut manifest = vec![];
+        match archive.by_name(BUNDLE_MANIFEST) {
+            Ok(mut file) => limits.copy(BUNDLE_MANIFEST, &mut file, &mut manifest)?,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut sums = vec![];
+        match archive.by_name(SUMFILE) {
+            Ok(mut file) => limits.copy(SUMFILE, &mut file, &mut sums)?,
+            Err(ZipError::FileNotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(Some((
+            toml::from_str(&String::from_utf8_lossy(&manifest))?,
+            String::from_utf8_lossy(&sums).into_owned(),
+        )))
+    }
+}
+
+/// Limits that protect against archives which expand to huge amounts of data.
+struct ExtractLimits {
+    /// How many more bytes can be extracted from the archive.
+    remaining_size: u64,
+    max_page_size: u64,
+    max_entries: usize,
+}
+
+impl ExtractLimits {
+    fn new(cfg: &CacheConfig) -> Self {
+        Self {
+            remaining_size: cfg.max_extracted_size(),
+            max_page_size: cfg.max_page_size(),
+            max_entries: cfg.max_archive_entries,
+        }
+    }
+
+    /// Copy `page` to `writer`, failing as soon as one of the limits is exceeded.
+    fn copy<R, W>(&mut self, name: &str, page: &mut R, writer: &mut W) -> Result<()>
+    where
+        R: Read,
+        W: Write,
+    {
+        // The sizes stored in the archive cannot be trusted, so the data is read
+        // until one byte past the limit.
+        let limit = self.max_page_size.min(self.remaining_size);
+        let n = io::copy(&mut page.take(limit.saturating_add(1)), writer)?;
+
+        if n > self.max_page_size {
+            return Err(Error::page_too_large(name, self.max_page_size));
+        }
+        if n > self.remaining_size {
+            return Err(Error::archive_limit(
+                "the archive is too large when extracted",
+                "max_extracted_size",
+            ));
+        }
+
+        self.remaining_size -= n;
+        Ok(())
+    }
+
+    /// Copy an archive stored in another archive to `writer`. Only the total size is limited,
+    /// the pages are checked when the archive is extracted.
+    fn copy_archive<R, W>(&mut self, archive: &mut R, writer: &mut W) -> Result<()>
+    where
+        R: Read,
+        W: Write,
+    {
+        let n = io::copy(
+            &mut archive.take(self.remaining_size.saturating_add(1)),
+            writer,
+        )?;
+
+        if n > self.remaining_size {
+            return Err(Error::archive_limit(
+                "the archive is too large when extracted",
+                "max_extracted_size",
+            ));
+        }
+
+        self.remaining_size -= n;
+        Ok(())
+    }
+}
+
+/// Counters of extracted pages.
+#[derive(Default)]
+struct PageCounter {
+    all_downloaded: i32,
+    all_new: i32,
+}
+
+impl PageCounter {
+    /// Update the counters with the results of extracting `lang_dir` and print them.
+    fn add(&mut self, lang_dir: &str, n_downloaded: i32, n_new: i32) -> Result<()> {
+        self.all_downloaded += n_downloaded;
+        self.all_new += n_new;
+
+        infoln!(
+            "'{lang_dir}': {} pages, {} new",
+            Paint::new(n_downloaded).fg(Green).bold(),
+            Paint::new(n_new).fg(Green).bold()
+        );
+
+        Ok(())
+    }
+}
+
+/// Where a page found by `Cache::find` is stored.
+#[derive(Clone, PartialEq, Eq)]
+pub enum PageSource {
+    /// The cache, either extracted or in an archive.
+    Cache,
+    /// A custom page directory.
+    Custom(PathBuf),
+}
+
+/// A page found by `Cache::find`. Use `Cache::open_page` to read it.
+#[derive(Clone)]
+pub struct PageRef {
+    /// The language of the page, `None` for custom pages.
+    pub lang: Option<String>,
+    pub platform: String,
+    /// The name of the page without the `.md` extension.
+    pub name: String,
+    pub source: PageSource,
+}
 
-type PagesArchive = ZipArchive<Cursor<Vec<u8>>>;
+impl Display for PageRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            lang,
+            platform,
+            name,
+            source,
+        } = self;
+
+        match (source, lang) {
+            (PageSource::Custom(dir), _) => {
+                write!(
+                    f,
+                    "{}",
+                    dir.join(platform).join(format!("{name}.md")).display()
+                )
+            }
+            (PageSource::Cache, Some(lang)) => write!(f, "pages.{lang}/{platform}/{name}.md"),
+            (PageSource::Cache, None) => write!(f, "{platform}/{name}.md"),
+        }
+    }
+}
 
 pub struct Cache<'a> {
     dir: &'a Path,
     platforms: OnceCell<Vec<OsString>>,
     age: OnceCell<Duration>,
+    /// Page stores of languages that have been accessed, `None` if not installed.
+    stores: RefCell<HashMap<String, Option<Rc<dyn PageStore>>>>,
+    /// The page index, `None` if it is missing or stale.
+    index: OnceCell<Option<Index>>,
+    /// Directories with custom pages (`<platform>/<page>.md`), in priority order.
+    custom_dirs: &'a [PathBuf],
+    /// The maximum size of a page read from an archive.
+    max_page_size: u64,
 }
 
 impl<'a> Cache<'a> {
@@ -31,89 +305,389 @@ impl<'a> Cache<'a> {
             dir,
             platforms: OnceCell::new(),
             age: OnceCell::new(),
+            stores: RefCell::new(HashMap::new()),
+            index: OnceCell::new(),
+            custom_dirs: &[],
+            max_page_size: u64::MAX,
         }
     }
 
+    /// Search `dirs` for custom pages before the cache.
+    pub fn custom_pages(mut self, dirs: &'a [PathBuf]) -> Self {
+        self.custom_dirs = dirs;
+        self
+    }
+
+    /// Refuse to read pages larger than `max_page_size` bytes from archives.
+    pub fn max_page_size(mut self, max_page_size: u64) -> Self {
+        self.max_page_size = max_page_size;
+        self
+    }
+
+    /// Get the custom page directories that exist.
+    fn custom_stores(&self) -> impl Iterator<Item = (&Path, DirStore)> {
+        self.custom_dirs
+            .iter()
+            .filter(|dir| dir.is_dir())
+            .map(|dir| (dir.as_path(), DirStore::new(dir)))
+    }
+
+    /// Forget everything that was read from the cache, after it has been modified.
+    fn reset(&mut self) {
+        self.platforms.take();
+        self.age.take();
+        self.stores.get_mut().clear();
+        self.index.take();
+    }
+
     /// Get the default path to the cache.
     pub fn locate() -> PathBuf {
         dirs::cache_dir().unwrap().join(env!("CARGO_PKG_NAME"))
     }
 
-    /// Return `true` if the specified subdirectory exists in the cache.
-    pub fn subdir_exists(&self, sd: &str) -> bool {
-        self.dir.join(sd).is_dir()
+    /// Get the path to the archive that stores pages from `lang_dir` in the zip storage mode.
+    fn lang_archive(&self, lang_dir: &str) -> PathBuf {
+        let lang = lang_dir.strip_prefix("pages.").unwrap_or(lang_dir);
+        self.dir.join(format!("tldr-pages.{lang}.zip"))
     }
 
-    /// Download tldr pages archives for directories that are out of date and update the checksum file.
-    fn download_and_verify(
+    /// Return `true` if pages from `lang_dir` are installed, either extracted or in an archive.
+    pub fn lang_installed(&self, lang_dir: &str) -> bool {
+        self.dir.join(lang_dir).is_dir() || self.lang_archive(lang_dir).is_file()
+    }
+
+    /// Return `true` if pages from `lang_dir` are installed using `storage`.
+    fn lang_stored_as(&self, lang_dir: &str, storage: StorageMode) -> bool {
+        match storage {
+            StorageMode::Dir => self.dir.join(lang_dir).is_dir(),
+            StorageMode::Zip => self.lang_archive(lang_dir).is_file(),
+        }
+    }
+
+    /// Get the page store for `lang_dir`. Returns `None` if the language is not installed.
+    fn store(&self, lang_dir: &str) -> Result<Option<Rc<dyn PageStore>>> {
+        if let Some(store) = self.stores.borrow().get(lang_dir) {
+            return Ok(store.clone());
+        }
+
+        let store = self.open_store(lang_dir)?;
+        self.stores
+            .borrow_mut()
+            .insert(lang_dir.to_string(), store.clone());
+        Ok(store)
+    }
+
+    /// Open the page store for `lang_dir` without caching it.
+    ///
+    /// Extracted pages take precedence over archives.
+    fn open_store(&self, lang_dir: &str) -> Result<Option<Rc<dyn PageStore>>> {
+        let dir = self.dir.join(lang_dir);
+        let archive = self.lang_archive(lang_dir);
+
+        let store: Option<Rc<dyn PageStore>> = if dir.is_dir() {
+            Some(Rc::new(DirStore::new(&dir)))
+        } else if archive.is_file() {
+            let store = ZipStore::open(&archive, self.max_page_size).map_err(|e| {
+                Error::new(format!("'{}': {e}", archive.display())).kind(ErrorKind::Io)
+            })?;
+            Some(Rc::new(store))
+        } else {
+            None
+        };
+
+        Ok(store)
+    }
+
+    /// Get the installed languages in alphabetical order.
+    fn languages(&self) -> Result<Vec<String>> {
+        match self.index() {
+            Some(index) => Ok(index.languages()),
+            None => self.scan_languages(),
+        }
+    }
+
+    /// Get the languages to search for pages in order: `languages`, with installed regional
+    /// variants after every language without a territory (e.g. `pt_BR` after `pt`).
+    fn language_chain(&self, languages: &[String]) -> Result<Vec<String>> {
+        Ok(util::add_language_variants(languages, &self.languages()?))
+    }
+
+    /// Get the first installed language from the language chain of `languages`.
+    pub fn preferred_language(&self, languages: &[String]) -> Result<Option<String>> {
+        Ok(self
+            .language_chain(languages)?
+            .into_iter()
+            .find(|lang| self.lang_installed(&format!("pages.{lang}"))))
+    }
+
+    /// Find out which languages are installed without using the index.
+    fn scan_languages(&self) -> Result<Vec<String>> {
+        let mut languages = vec![];
+
+        for entry in fs::read_dir(self.dir)? {
+            let entry = entry?;
+            let fname = entry.file_name();
+            let fname = fname.to_string_lossy();
+
+            // Staging directories and other files are skipped.
+            let lang = if entry.path().is_dir() {
+                fname.strip_prefix("pages.")
+            } else {
+                fname
+                    .strip_prefix("tldr-pages.")
+                    .and_then(|x| x.strip_suffix(".zip"))
+            };
+
+            if let Some(lang) = lang {
+                languages.push(lang.to_string());
+            }
+        }
+
+        languages.sort_unstable();
+        // A language can be both extracted and in an archive after an interrupted update.
+        languages.dedup();
+
+        Ok(languages)
+    }
+
+    /// Download the signature of the checksum file and verify it using the trusted `keys`.
+    ///
+    /// Returns the hex-encoded key that made the signature.
+    fn verify_sums(mirror: &Mirror, sums: &str, keys: &[Vec<u8>]) -> Result<String> {
+        infoln!("verifying the signature of '{SUMFILE}'...");
+
+        // This is safe to unwrap, there is no conditional request.
+        let signature = mirror
+            .fetch(SUMFILE_SIG, &Validators::default(), |fetched| {
+                let mut signature = vec![];
+                fetched.copy_to(&mut signature)?;
+                Ok(signature)
+            })
+            .map_err(|e| {
+                let message = format!("could not download the signature of '{SUMFILE}': {e}");
+                Error::new(message).kind(e.kind)
+            })?
+            .unwrap();
+
+        keys.iter()
+            .find(|key| {
+                UnparsedPublicKey::new(&ED25519, key)
+                    .verify(sums.as_bytes(), &signature)
+                    .is_ok()
+            })
+            .map(|key| util::hex_encode(key))
+            .ok_or_else(Error::bad_signature)
+    }
+
+    /// Download the checksum file and find out which languages are out of date.
+    /// If any `keys` are specified, the signature of the checksum file is verified.
+    ///
+    /// Returns the contents of the new checksum file, its HTTP headers
+    /// and a map of outdated languages to their sums.
+    fn download_sums(
         &self,
-        mirror: &str,
+        mirror: &Mirror,
+        cfg: &CacheConfig,
         languages: &[String],
-    ) -> Result<BTreeMap<String, PagesArchive>> {
-        let agent = ureq::builder()
-            .user_agent(USER_AGENT)
-            .try_proxy_from_env(true)
-            .build();
-        let old_sumfile_path = self.dir.join("tldr.sha256sums");
-        let mut langdir_archive_map = BTreeMap::new();
+    ) -> Result<(String, SumfileHeaders, BTreeMap<String, String>)> {
+        let keys = cfg.public_keys()?;
+        let old_sums = fs::read_to_string(self.dir.join(SUMFILE)).unwrap_or_default();
+        let old_sum_map = Self::parse_sumfile(&old_sums).unwrap_or_default();
+        let old_headers = SumfileHeaders::parse(
+            &fs::read_to_string(self.dir.join(SUMFILE_HEADERS)).unwrap_or_default(),
+        );
 
         infoln!("downloading 'tldr.sha256sums'...");
-        let sums = agent
-            .get(&format!("{mirror}/tldr.sha256sums"))
-            .call()?
-            .into_string()?;
-        let sum_map = Self::parse_sumfile(&sums)?;
 
-        let old_sums = fs::read_to_string(&old_sumfile_path).unwrap_or_default();
-        let old_sum_map = Self::parse_sumfile(&old_sums).unwrap_or_default();
+        // A conditional request can only be made if the old checksum file was used to check
+        // all languages that are requested now. Otherwise, newly added languages would not
+        // be downloaded until the checksum file changes on the mirror.
+        let conditional = !old_sums.is_empty()
+            && languages.iter().all(|lang| {
+                old_headers.languages.contains(lang)
+                    && (!old_sum_map.contains_key(&**lang)
+                        || self.lang_stored_as(&format!("pages.{lang}"), cfg.storage))
+            })
+            // The old checksum file must also have been signed by one of the trusted keys,
+            // otherwise an unverified file could be kept forever.
+            && (keys.is_empty()
+                || old_headers
+                    .signed_by
+                    .as_ref()
+                    .is_some_and(|k| keys.iter().any(|key| util::hex_encode(key) == *k)));
+
+        // Validators are only valid for the mirror that returned them.
+        let mirror_url = mirror.to_string();
+        let no_validators = Validators::default();
+        let validators = if conditional {
+            old_headers
+                .validators
+                .get(&mirror_url)
+                .unwrap_or(&no_validators)
+        } else {
+            &no_validators
+        };
 
-        for lang in languages {
-            let lang = &**lang;
+        let fetched = mirror.fetch(SUMFILE, validators, |fetched| {
+            Ok((fetched.read_to_string()?, fetched.validators.clone()))
+        })?;
+
+        let Some((sums, new_validators)) = fetched else {
+            infoln!("'tldr.sha256sums' has not changed");
+            return Ok((old_sums, old_headers, BTreeMap::new()));
+        };
+
+        let signed_by = if keys.is_empty() {
+            None
+        } else {
+            Some(Self::verify_sums(mirror, &sums, &keys)?)
+        };
+        // Validators of other mirrors can be kept if they served the same checksum file.
+        let mut validators = if sums == old_sums {
+            old_headers.validators
+        } else {
+            BTreeMap::new()
+        };
+        if new_validators.is_empty() {
+            validators.remove(&mirror_url);
+        } else {
+            validators.insert(mirror_url, new_validators);
+        }
+        let headers = SumfileHeaders {
+            validators,
+            languages: languages.to_vec(),
+            signed_by,
+        };
+        let sum_map = Self::parse_sumfile(&sums)?;
+        let available: Vec<&str> = sum_map.keys().copied().collect();
+        let mut outdated = BTreeMap::new();
+
+        // Regional variants are downloaded too, so that they can be used as fallbacks.
+        for lang in util::add_language_variants(languages, &available) {
+            let lang = &*lang;
             let Some(sum) = sum_map.get(lang) else {
                 // Skip nonexistent languages.
                 continue;
             };
 
-            let lang_dir = format!("pages.{lang}");
-            if Some(sum) == old_sum_map.get(lang) && self.subdir_exists(&lang_dir) {
+            // Languages stored differently than configured are downloaded again.
+            if Some(sum) == old_sum_map.get(lang)
+                && self.lang_stored_as(&format!("pages.{lang}"), cfg.storage)
+            {
                 infoln!("'pages.{lang}' is up to date");
                 continue;
             }
 
-            infoln!("downloading 'tldr-pages.{lang}.zip'...");
+            outdated.insert(lang.to_string(), (*sum).to_string());
+        }
 
-            let resp = agent
-                .get(&format!("{mirror}/tldr-pages.{lang}.zip"))
-                .call()?;
-            let archive_len = resp
-                .header("Content-Length")
-                .unwrap_or_default()
-                .parse()
-                .unwrap_or_default();
-            let mut archive = Vec::with_capacity(archive_len);
-            resp.into_reader().read_to_end(&mut archive)?;
+        Ok((sums, headers, outdated))
+    }
 
-            info_start!("validating sha256sums...");
-            let actual_sum = util::sha256_hexdigest(&archive);
+    /// Write the checksum file and its HTTP headers to `dir` and move them into the cache.
+    ///
+    /// The headers are written last, so that they never belong to an older checksum file.
+    fn write_sumfile(&self, dir: &Path, sums: &str, headers: &SumfileHeaders) -> Result<()> {
+        for (fname, contents) in [(SUMFILE, sums), (SUMFILE_HEADERS, &headers.to_string())] {
+            let path = dir.join(fname);
+            File::create(&path)?.write_all(contents.as_bytes())?;
+            fs::rename(path, self.dir.join(fname))?;
+        }
 
-            if sum != &actual_sum {
-                info_end!(" {}", Paint::new("FAILED").fg(Red).bold());
-                return Err(Error::new(format!(
-                    "SHA256 sum mismatch!\n\
-                    expected : {sum}\n\
-                    got      : {actual_sum}"
-                )));
-            }
+        Ok(())
+    }
 
-            info_end!(" {}", Paint::new("OK").fg(Green).bold());
+    /// Download the archive for `lang` into `staging`, verify it and extract it.
+    /// In the zip storage mode, the archive is only checked and kept in `staging`.
+    ///
+    /// Returns the number of extracted pages.
+    fn download_and_extract(
+        mirror: &Mirror,
+        lang: &str,
+        sum: &str,
+        staging: &Path,
+        cfg: &CacheConfig,
+    ) -> Result<i32> {
+        let fname = format!("tldr-pages.{lang}.zip");
+        let archive_path = staging.join(&fname);
+
+        // The archive is hashed while it is being written to disk,
+        // so that it never has to be held in memory.
+        // This is safe to unwrap, there is no conditional request.
+        let actual_sum = mirror
+            .fetch(&fname, &Validators::default(), |fetched| {
+                let mut writer = Sha256Writer::new(BufWriter::new(File::create(&archive_path)?));
+                fetched.copy_to(&mut writer)?;
+                let (file, actual_sum) = writer.finish();
+                file.into_inner().map_err(io::IntoInnerError::into_error)?;
+                Ok(actual_sum)
+            })?
+            .unwrap();
+
+        if sum != actual_sum {
+            return Err(Error::sum_mismatch(sum, &actual_sum));
+        }
 
-            langdir_archive_map.insert(lang_dir, ZipArchive::new(Cursor::new(archive))?);
+        Self::extract_staged(lang, staging, cfg)
+    }
+
+    /// Extract the verified archive of `lang` in `staging`.
+    /// In the zip storage mode, the archive is only checked and kept in `staging`.
+    ///
+    /// Returns the number of extracted pages.
+    fn extract_staged(lang: &str, staging: &Path, cfg: &CacheConfig) -> Result<i32> {
+        let archive_path = staging.join(format!("tldr-pages.{lang}.zip"));
+        let mut archive = ZipArchive::new(BufReader::new(File::open(&archive_path)?))?;
+        let dest = match cfg.storage {
+            StorageMode::Dir => Some(staging),
+            StorageMode::Zip => None,
+        };
+        let n_extracted = Self::extract_lang_archive(
+            dest,
+            &format!("pages.{lang}"),
+            &mut archive,
+            "",
+            &mut ExtractLimits::new(cfg),
+        )?;
+
+        if dest.is_some() {
+            fs::remove_file(archive_path)?;
         }
 
-        fs::create_dir_all(self.dir)?;
-        File::create(&old_sumfile_path)?.write_all(sums.as_bytes())?;
+        Ok(n_extracted)
+    }
+
+    /// Run `download_and_extract` for every outdated language using `cfg.download_threads`
+    /// threads.
+    ///
+    /// Results are returned in alphabetical order.
+    fn download_and_extract_all<'l>(
+        mirror: &Mirror,
+        outdated: &'l BTreeMap<String, String>,
+        staging: &Path,
+        cfg: &CacheConfig,
+    ) -> BTreeMap<&'l str, Result<i32>> {
+        let queue = Mutex::new(outdated.iter());
+        let results = Mutex::new(BTreeMap::new());
+
+        thread::scope(|s| {
+            for _ in 0..cfg.download_threads.clamp(1, outdated.len()) {
+                s.spawn(|| loop {
+                    let Some((lang, sum)) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+
+                    let result = Self::download_and_extract(mirror, lang, sum, staging, cfg)
+                        .map_err(|e| {
+                            let message = format!("'tldr-pages.{lang}.zip': {e}");
+                            Error::new(message).kind(e.kind)
+                        });
+                    results.lock().unwrap().insert(lang.as_str(), result);
+                });
+            }
+        });
 
-        Ok(langdir_archive_map)
+        results.into_inner().unwrap()
     }
 
     fn parse_sumfile(s: &str) -> Result<HashMap<&str, &str>> {
@@ -148,97 +722,1238 @@ impl<'a> Cache<'a> {
         Ok(map)
     }
 
-    /// Extract pages from the language archive and update the page counters.
+    /// Create a file for an extracted page. Permissions stored in the archive are ignored.
+    fn create_page_file(path: &Path) -> io::Result<File> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o644);
+
+        options.open(path)
+    }
+
+    /// Extract pages from the language archive into `dest` and return the number of pages.
+    /// If `dest` is `None`, the entries are only checked.
+    ///
+    /// Only entries starting with `prefix` are extracted, with the prefix stripped.
+    /// Entries that would end up outside of `lang_dir` and entries that are neither
+    /// regular files nor directories (e.g. symlinks) make the extraction fail,
+    /// as well as archives that exceed `limits`.
     fn extract_lang_archive(
-        &self,
+        dest: Option<&Path>,
         lang_dir: &str,
         archive: &mut PagesArchive,
-        n_existing: i32,
-        all_downloaded: &mut i32,
-        all_new: &mut i32,
-    ) -> Result<()> {
-        info_start!("extracting '{lang_dir}'...");
+        prefix: &str,
+        limits: &mut ExtractLimits,
+    ) -> Result<i32> {
+        if archive.len() > limits.max_entries {
+            return Err(Error::archive_limit(
+                &format!(
+                    "the archive contains more than {} entries",
+                    limits.max_entries
+                ),
+                "max_archive_entries",
+            ));
+        }
 
         let mut n_downloaded = 0;
 
         for i in 0..archive.len() {
-            let mut page = archive.by_index(i).unwrap();
-            let fname = page.name();
+            let mut page = archive.by_index(i)?;
+            let name = page.name().to_string();
+
+            // `enclosed_name` rejects absolute paths and paths that lead outside of the archive.
+            let Some(enclosed) = page.enclosed_name() else {
+                return Err(Error::bad_archive_entry(
+                    &name,
+                    "the path leads outside of the archive",
+                ));
+            };
+            let Ok(fname) = enclosed.strip_prefix(prefix) else {
+                continue;
+            };
+            let fname = fname.to_path_buf();
+
+            // Paths like `pages.de/../pages.en/...` stay in the archive, but not in `lang_dir`.
+            if !fname
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(Error::bad_archive_entry(
+                    &name,
+                    &format!("the path leads outside of '{lang_dir}'"),
+                ));
+            }
+
+            let file_type = page.unix_mode().map_or(0, |mode| mode & S_IFMT);
+            if file_type != 0 && file_type != S_IFREG && file_type != S_IFDIR {
+                return Err(Error::bad_archive_entry(
+                    &name,
+                    "not a regular file or directory",
+                ));
+            }
+
+            let path = dest.map(|dest| dest.join(lang_dir).join(&fname));
+
+            if page.is_dir() {
+                if let Some(path) = path {
+                    fs::create_dir_all(path)?;
+                }
+                continue;
+            }
 
             // Skip files that are not in a directory (we want only pages).
-            if !fname.contains('/') {
+            if fname.parent().map_or(true, |p| p.as_os_str().is_empty()) {
+                continue;
+            }
+
+            if let Some(path) = path {
+                // Archives created by hand might not contain entries for directories.
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                let mut file = Self::create_page_file(&path)?;
+                limits.copy(&name, &mut page, &mut file)?;
+            } else {
+                limits.copy(&name, &mut page, &mut io::sink())?;
+            }
+
+            n_downloaded += 1;
+        }
+
+        Ok(n_downloaded)
+    }
+
+    /// Get the path to the staging directory used by this process.
+    fn staging_dir(&self) -> PathBuf {
+        self.dir.join(format!("{STAGING_PREFIX}{}", process::id()))
+    }
+
+    /// Return `true` if another process is modifying the cache.
+    pub fn is_locked(&self) -> bool {
+        CacheLock::is_held(self.dir)
+    }
+
+    /// Remove staging directories left behind by interrupted updates,
+    /// unless another process is modifying the cache right now.
+    ///
+    /// The cache is only locked if there is something to clean up. Errors (e.g. when the cache
+    /// is read-only) are reported as warnings, because the existing pages can still be shown.
+    pub fn remove_stale_staging(&self) -> Result<()> {
+        let has_staging = fs::read_dir(self.dir).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|e| e.file_name().to_string_lossy().starts_with(STAGING_PREFIX))
+        });
+
+        if !has_staging {
+            return Ok(());
+        }
+
+        let result = match CacheLock::try_acquire(self.dir) {
+            Ok(Some(_lock)) => self.cleanup_staging(),
+            // The staging directory belongs to an update that is still in progress.
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            warnln!("could not clean up after an interrupted cache update: {e}");
+        }
+
+        Ok(())
+    }
+
+    /// Remove staging directories left behind by interrupted updates.
+    /// The cache must be locked.
+    ///
+    /// Language directories and archives that were moved out of the way but never replaced
+    /// are restored.
+    fn cleanup_staging(&self) -> Result<()> {
+        for entry in fs::read_dir(self.dir)? {
+            let entry = entry?;
+            let staging = entry.path();
+            if !entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(STAGING_PREFIX)
+                || !staging.is_dir()
+            {
+                continue;
+            }
+
+            warnln!("cleaning up after an interrupted cache update...");
+
+            for old in fs::read_dir(&staging)? {
+                let old = old?.file_name();
+                let old = old.to_string_lossy();
+                let Some(lang_dir) = old.strip_suffix(".old") else {
+                    continue;
+                };
+
+                let lang_dir_full = self.dir.join(lang_dir);
+                if !lang_dir_full.exists() {
+                    fs::rename(staging.join(&*old), lang_dir_full)?;
+                }
+            }
+
+            fs::remove_dir_all(&staging)?;
+        }
+
+        Ok(())
+    }
+
+    /// Download and extract all outdated archives from `mirror` into `staging`, then swap
+    /// the new language directories into the cache and write the checksum file.
+    ///
+    /// Returns `true` if any languages were replaced.
+    fn update_from(
+        &self,
+        mirror: &Mirror,
+        staging: &Path,
+        cfg: &CacheConfig,
+        languages: &[String],
+    ) -> Result<bool> {
+        let (sums, headers, outdated) = self.download_sums(mirror, cfg, languages)?;
+
+        if outdated.is_empty() {
+            // Refresh the age of the cache.
+            self.write_sumfile(staging, &sums, &headers)?;
+            infoln!(
+                "there is nothing to do. Run 'tldr --clean-cache' if you want to force an update."
+            );
+            return Ok(false);
+        }
+
+        let (counter, changelog) = self.install_from(mirror, staging, cfg, &outdated)?;
+        changelog.append_to(&self.dir.join(CHANGELOG))?;
+
+        // The checksum file is written last, so that an interrupted update is retried.
+        self.write_sumfile(staging, &sums, &headers)?;
+
+        infoln!(
+            "cache update successful (total: {} pages, {} new).",
+            Paint::new(counter.all_downloaded).fg(Green).bold(),
+            Paint::new(counter.all_new).fg(Green).bold(),
+        );
+
+        Ok(true)
+    }
+
+    /// Download and extract `outdated` archives from `mirror` into `staging`, then swap
+    /// the new pages into the cache and record their hashes.
+    ///
+    /// Returns the page counts and the changes of pages compared to the old cache.
+    fn install_from(
+        &self,
+        mirror: &Mirror,
+        staging: &Path,
+        cfg: &CacheConfig,
+        outdated: &BTreeMap<String, String>,
+    ) -> Result<(PageCounter, Changelog)> {
+        infoln!(
+            "downloading {} archive(s) using {} thread(s)...",
+            outdated.len(),
+            cfg.download_threads.clamp(1, outdated.len())
+        );
+
+        let results = Self::download_and_extract_all(mirror, outdated, staging, cfg);
+        let mut downloaded = vec![];
+        for (lang, result) in results {
+            downloaded.push((lang, result?));
+        }
+
+        // Every archive has been extracted successfully, the old directories can be replaced.
+        let (counter, changelog, hashes) = self.diff_staged(staging, &downloaded)?;
+        self.swap_in(staging, outdated.keys())?;
+        self.write_page_sums(staging, outdated.keys(), hashes)?;
+
+        Ok((counter, changelog))
+    }
+
+    /// Hash the pages extracted into `staging` and compare them with the installed ones.
+    ///
+    /// `downloaded` contains the number of extracted pages for every language.
+    /// Returns the page counts, the changes and the sums of the extracted pages.
+    fn diff_staged(
+        &self,
+        staging: &Path,
+        downloaded: &[(&str, i32)],
+    ) -> Result<(PageCounter, Changelog, BTreeMap<String, String>)> {
+        let hashes = Self::hash_pages(staging, downloaded.iter().map(|(lang, _)| lang))?;
+        let recorded = self.read_page_sums();
+        let mut counter = PageCounter::default();
+        let mut changelog = Changelog::default();
+        let now = Self::now();
+
+        for &(lang, n_downloaded) in downloaded {
+            let n_new = self
+                .diff_lang(staging, lang, now, (&recorded, &hashes), &mut changelog)?
+                .unwrap_or(n_downloaded);
+            counter.add(&format!("pages.{lang}"), n_downloaded, n_new)?;
+        }
+
+        Ok((counter, changelog, hashes))
+    }
+
+    /// Get the current time in seconds since the Unix epoch.
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+
+    /// Compare the installed pages of `lang` with the ones extracted into `staging` and
+    /// add the differences to `changelog`.
+    ///
+    /// Extracted pages are compared using `sums` (the recorded sums of the installed pages and
+    /// the sums of the staged ones), so that they do not have to be hashed again. Pages kept
+    /// in archives do not have these, so they are hashed here.
+    ///
+    /// Returns the number of added pages, or `None` if the language was not installed
+    /// (pages of newly installed languages are not recorded as added).
+    fn diff_lang(
+        &self,
+        staging: &Path,
+        lang: &str,
+        time: u64,
+        (recorded, staged): (&BTreeMap<String, String>, &BTreeMap<String, String>),
+        changelog: &mut Changelog,
+    ) -> Result<Option<i32>> {
+        let lang_dir = format!("pages.{lang}");
+        // A damaged old store should not prevent the update.
+        let Ok(Some(old_store)) = self.open_store(&lang_dir) else {
+            return Ok(None);
+        };
+        let Some(new_store) = Cache::new(staging).open_store(&lang_dir)? else {
+            return Ok(None);
+        };
+
+        let old = match Self::lang_page_sums(recorded, &lang_dir) {
+            Some(sums) => sums,
+            None => match Changelog::hash_store(&*old_store) {
+                Ok(sums) => sums,
+                Err(_) => return Ok(None),
+            },
+        };
+        let new = match Self::lang_page_sums(staged, &lang_dir) {
+            Some(sums) => sums,
+            None => Changelog::hash_store(&*new_store)?,
+        };
+
+        Ok(Some(changelog.diff(time, lang, &old, &new)))
+    }
+
+    /// Get the sums of pages in `lang_dir` from `sums` (keyed by paths relative to the cache).
+    ///
+    /// Returns `None` if there are no sums for `lang_dir`.
+    fn lang_page_sums(sums: &BTreeMap<String, String>, lang_dir: &str) -> Option<PageSums> {
+        let prefix = format!("{lang_dir}/");
+        let page_sums: PageSums = sums
+            .range(prefix.clone()..)
+            .map_while(|(path, sum)| Some((path.strip_prefix(&prefix)?, sum)))
+            .filter_map(|(path, sum)| {
+                let (platform, fname) = path.split_once('/')?;
+                let name = fname.strip_suffix(".md").filter(|n| !n.contains('/'))?;
+                Some(((platform.to_string(), name.to_string()), sum.clone()))
+            })
+            .collect();
+
+        (!page_sums.is_empty()).then_some(page_sums)
+    }
+
+    /// Calculate the SHA256 sum of a file.
+    fn hash_file(path: &Path) -> Result<String> {
+        let mut writer = Sha256Writer::new(io::sink());
+        io::copy(&mut File::open(path)?, &mut writer)?;
+        Ok(writer.finish().1)
+    }
+
+    /// List all files in `dir` recursively, with paths relative to `dir` separated by `/`.
+    fn walk(dir: &Path, prefix: &str, files: &mut BTreeSet<String>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+
+            if entry.file_type()?.is_dir() {
+                Self::walk(&entry.path(), &format!("{name}/"), files)?;
+            } else {
+                files.insert(name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calculate the sums of all pages of `languages` extracted into `staging`.
+    ///
+    /// Returns a map of paths relative to the cache directory to sums.
+    fn hash_pages<I, S>(staging: &Path, languages: I) -> Result<BTreeMap<String, String>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Display,
+    {
+        let mut hashes = BTreeMap::new();
+
+        for lang in languages {
+            let lang_dir = format!("pages.{lang}");
+            let dir = staging.join(&lang_dir);
+            // Languages kept in archives are verified using the checksum file.
+            if !dir.is_dir() {
+                continue;
+            }
+
+            let mut files = BTreeSet::new();
+            Self::walk(&dir, "", &mut files)?;
+
+            for file in files {
+                let sum = Self::hash_file(&dir.join(&file))?;
+                hashes.insert(format!("{lang_dir}/{file}"), sum);
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    /// Read the recorded sums of extracted pages.
+    fn read_page_sums(&self) -> BTreeMap<String, String> {
+        let sums = fs::read_to_string(self.dir.join(PAGE_SUMS)).unwrap_or_default();
+
+        sums.lines()
+            .filter_map(|l| l.split_once("  "))
+            .map(|(sum, path)| (path.to_string(), sum.to_string()))
+            .collect()
+    }
+
+    /// Replace the recorded sums of `languages` with `hashes`.
+    fn write_page_sums<I, S>(
+        &self,
+        staging: &Path,
+        languages: I,
+        mut hashes: BTreeMap<String, String>,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: Display,
+    {
+        let prefixes: Vec<String> = languages
+            .into_iter()
+            .map(|lang| format!("pages.{lang}/"))
+            .collect();
+
+        for (path, sum) in self.read_page_sums() {
+            if !prefixes.iter().any(|prefix| path.starts_with(prefix)) {
+                hashes.entry(path).or_insert(sum);
+            }
+        }
+
+        // The file has the same format as the output of sha256sum(1).
+        let mut contents = String::new();
+        for (path, sum) in hashes {
+            let _ = writeln!(contents, "{sum}  {path}");
+        }
+
+        let path = staging.join(PAGE_SUMS);
+        fs::write(&path, contents)?;
+        Ok(fs::rename(path, self.dir.join(PAGE_SUMS))?)
+    }
+
+    /// Replace language directories and archives in the cache with the ones in `staging`.
+    fn swap_in<I, S>(&self, staging: &Path, languages: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: Display,
+    {
+        // The index is rebuilt after the languages are replaced. It is removed first, so that
+        // an interrupted update cannot leave an index of the old pages behind.
+        match fs::remove_file(self.dir.join(INDEX)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        for lang in languages {
+            let names = [format!("pages.{lang}"), format!("tldr-pages.{lang}.zip")];
+
+            // The old pages are moved out of the way regardless of how they are stored,
+            // because extracted pages would take precedence over a new archive.
+            for name in &names {
+                let old = self.dir.join(name);
+                if old.exists() {
+                    fs::rename(old, staging.join(format!("{name}.old")))?;
+                }
+            }
+
+            for name in &names {
+                let new = staging.join(name);
+                if new.exists() {
+                    fs::rename(new, self.dir.join(name))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run `f` with every configured mirror in order, until it succeeds.
+    /// `staging` is emptied before the next mirror is tried.
+    fn try_mirrors<T, F>(staging: &Path, cfg: &CacheConfig, mut f: F) -> Result<T>
+    where
+        F: FnMut(&Mirror) -> Result<T>,
+    {
+        let urls = cfg.mirror.urls();
+        let mut errors = vec![];
+
+        for (i, url) in urls.iter().enumerate() {
+            let mirror = Mirror::new(url, cfg);
+
+            match f(&mirror) {
+                Ok(x) => return Ok(x),
+                // Only download errors can be fixed by switching to another mirror.
+                Err(e) if matches!(e.kind, ErrorKind::Download) => {
+                    if i + 1 != urls.len() {
+                        warnln!("'{mirror}' failed, trying the next mirror...");
+                        // Start over with an empty staging directory.
+                        fs::remove_dir_all(staging)?;
+                        fs::create_dir_all(staging)?;
+                    }
+
+                    errors.push((mirror.to_string(), e));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if errors.len() == 1 {
+            Err(errors.pop().unwrap().1)
+        } else {
+            Err(Error::all_mirrors_failed(errors))
+        }
+    }
+
+    /// Get the stamp that identifies the installed pages: the SHA256 sum of the checksum file,
+    /// which is replaced when pages are downloaded or imported.
+    fn index_stamp(&self) -> String {
+        Self::hash_file(&self.dir.join(SUMFILE)).unwrap_or_default()
+    }
+
+    /// Get the page index, or `None` if it is missing or stale.
+    fn index(&self) -> Option<&Index> {
+        self.index
+            .get_or_init(|| Index::load(&self.dir.join(INDEX), &self.index_stamp()))
+            .as_ref()
+    }
+
+    /// Build the page index in `staging` and move it into the cache.
+    fn write_index(&self, staging: &Path) -> Result<()> {
+        let mut index = IndexBuilder::new(self.index_stamp());
+
+        for lang in self.scan_languages()? {
+            // Stores opened before the update contain old pages, so new ones are opened.
+            if let Some(store) = self.open_store(&format!("pages.{lang}"))? {
+                index.add(&lang, &*store)?;
+            }
+        }
+
+        let path = staging.join(INDEX);
+        index.write(&path)?;
+        Ok(fs::rename(path, self.dir.join(INDEX))?)
+    }
+
+    /// Download archives, extract them into a staging directory
+    /// and replace the old language directories.
+    pub fn update(&mut self, cfg: &CacheConfig) -> Result<()> {
+        let mut languages = cfg.languages.clone();
+        // Sort to always download archives in alphabetical order.
+        languages.sort_unstable();
+        // The user can put duplicates in the config file.
+        languages.dedup();
+
+        let _lock = CacheLock::acquire(self.dir, LOCK_TIMEOUT)?;
+        self.cleanup_staging()?;
+
+        let staging = self.staging_dir();
+        fs::create_dir_all(&staging)?;
+
+        let result = Self::try_mirrors(&staging, cfg, |mirror| {
+            self.update_from(mirror, &staging, cfg, &languages)
+        })
+        .and_then(|replaced| {
+            // The index only has to be rebuilt if pages have changed.
+            if replaced || self.index().is_none() {
+                self.write_index(&staging)
+            } else {
+                Ok(())
+            }
+        });
+        // The staging directory only contains old or partially extracted pages at this point.
+        let cleanup = fs::remove_dir_all(&staging);
+        self.reset();
+        result?;
+
+        Ok(cleanup?)
+    }
+
+    /// Find the expected sum of `archive_path` in a checksum file next to it.
+    fn find_sum_next_to(archive_path: &Path) -> Result<Option<String>> {
+        let Ok(sums) = fs::read_to_string(archive_path.with_file_name(SUMFILE)) else {
+            return Ok(None);
+        };
+        let fname = archive_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+
+        for l in sums.lines() {
+            let mut spl = l.split_whitespace();
+            let sum = spl.next().ok_or_else(Error::parse_sumfile)?;
+            let path = spl.next().ok_or_else(Error::parse_sumfile)?;
+
+            if path.rsplit('/').next() == Some(&fname) {
+                return Ok(Some(sum.to_string()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Find out which languages are in `archive` and the prefixes of their entries.
+    ///
+    /// Full archives (`tldr.zip`) contain directories named `pages` (English) and
+    /// `pages.<lang>`, whereas language archives (`tldr-pages.<lang>.zip`) contain
+    /// platform directories directly.
+    fn archive_languages(
+        archive: &PagesArchive,
+        archive_path: &Path,
+        languages: Option<&[String]>,
+        is_bundle: bool,
+    ) -> Result<BTreeMap<String, String>> {
+        let mut result = BTreeMap::new();
+
+        for name in archive.file_names() {
+            let Some((dir, _)) = name.split_once('/') else {
+                continue;
+            };
+            let lang = if dir == "pages" {
+                "en"
+            } else if let Some(lang) = dir.strip_prefix("pages.") {
+                lang
+            } else {
                 continue;
+            };
+
+            if languages.map_or(true, |l| l.iter().any(|x| x == lang)) {
+                result.insert(lang.to_string(), format!("{dir}/"));
             }
+        }
 
-            let path = self.dir.join(lang_dir).join(fname);
+        // Bundles can also contain language archives, see `bundle_archives`.
+        if !result.is_empty() || is_bundle {
+            return Ok(result);
+        }
+
+        let lang = match languages {
+            Some([lang]) => lang.clone(),
+            Some(_) => {
+                return Err(Error::new(
+                    "only one language can be specified when importing a language archive.",
+                ));
+            }
+            None => {
+                let fname = archive_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                fname
+                    .strip_prefix("tldr-pages.")
+                    .and_then(|x| x.strip_suffix(".zip"))
+                    .filter(|x| !x.is_empty())
+                    .ok_or_else(|| {
+                        Error::new(
+                            "could not determine the language of the archive. \
+                            Please specify it using --language.",
+                        )
+                    })?
+                    .to_string()
+            }
+        };
+
+        result.insert(lang, String::new());
+        Ok(result)
+    }
+
+    /// Find the languages of archives (`tldr-pages.<lang>.zip`) stored in a bundle.
+    fn bundle_archives(archive: &PagesArchive, languages: Option<&[String]>) -> BTreeSet<String> {
+        archive
+            .file_names()
+            .filter_map(|name| name.strip_prefix("tldr-pages.")?.strip_suffix(".zip"))
+            .filter(|lang| !lang.is_empty() && !lang.contains(['/', '\\']))
+            .filter(|lang| languages.map_or(true, |l| l.iter().any(|x| x == lang)))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Extract the archive of `lang` stored in a bundle into `staging`.
+    ///
+    /// The archive is verified using `sum` from the checksum file in the bundle.
+    /// Returns the number of extracted pages.
+    fn extract_bundled_archive(
+        staging: &Path,
+        lang: &str,
+        bundle: &mut PagesArchive,
+        sum: &str,
+        limits: &mut ExtractLimits,
+    ) -> Result<i32> {
+        let fname = format!("tldr-pages.{lang}.zip");
+        let archive_path = staging.join(&fname);
+
+        let mut writer = Sha256Writer::new(BufWriter::new(File::create(&archive_path)?));
+        limits.copy_archive(&mut bundle.by_name(&fname)?, &mut writer)?;
+        let (file, actual_sum) = writer.finish();
+        file.into_inner().map_err(io::IntoInnerError::into_error)?;
+
+        if sum != actual_sum {
+            let e = Error::sum_mismatch(sum, &actual_sum);
+            return Err(Error::new(format!("'{fname}': {e}")).kind(e.kind));
+        }
+
+        let mut archive = ZipArchive::new(BufReader::new(File::open(&archive_path)?))?;
+        let n_extracted = Self::extract_lang_archive(
+            Some(staging),
+            &format!("pages.{lang}"),
+            &mut archive,
+            "",
+            limits,
+        )?;
+        fs::remove_file(archive_path)?;
+
+        Ok(n_extracted)
+    }
+
+    /// Install pages from `archive_path` into the cache using `staging`.
+    fn import_staged(
+        &self,
+        staging: &Path,
+        cfg: &CacheConfig,
+        archive_path: &Path,
+        languages: Option<&[String]>,
+    ) -> Result<()> {
+        let sum = Self::find_sum_next_to(archive_path)?;
+
+        if let Some(sum) = &sum {
+            let mut writer = Sha256Writer::new(io::sink());
+            io::copy(&mut File::open(archive_path)?, &mut writer)?;
+            let (_, actual_sum) = writer.finish();
+
+            if *sum != actual_sum {
+                return Err(Error::sum_mismatch(sum, &actual_sum));
+            }
+
+            infoln!("the archive matches the sum in '{SUMFILE}'");
+        } else {
+            warnln!(
+                "no sum for the archive found in '{SUMFILE}' next to it, skipping verification"
+            );
+        }
+
+        let mut archive = ZipArchive::new(BufReader::new(File::open(archive_path)?))?;
+        // Languages and metadata from the same archive share the limits.
+        let mut limits = ExtractLimits::new(cfg);
+        let bundle = BundleManifest::read(&mut archive, &mut limits)?;
+
+        if let Some((manifest, _)) = &bundle {
+            let age = Self::now().saturating_sub(manifest.created);
+
+            infoln!(
+                "importing a bundle created {} ago by tlrc {} (languages: {})",
+                Paint::new(util::duration_fmt(age)).fg(Green).bold(),
+                manifest.version,
+                manifest.languages.join(", ")
+            );
+        }
+
+        let lang_prefixes =
+            Self::archive_languages(&archive, archive_path, languages, bundle.is_some())?;
+        let (bundle_sums, bundled_archives) = match &bundle {
+            Some((_, sums)) => (
+                Self::parse_sumfile(sums)?,
+                Self::bundle_archives(&archive, languages),
+            ),
+            None => (HashMap::new(), BTreeSet::new()),
+        };
+        let imported: BTreeSet<&String> = lang_prefixes.keys().chain(&bundled_archives).collect();
+
+        if imported.is_empty() {
+            return Err(Error::new(
+                "the bundle does not contain any of the languages.",
+            ));
+        }
+
+        let mut downloaded = vec![];
+
+        for lang in &imported {
+            let lang_dir = format!("pages.{lang}");
+            let n_downloaded = if bundled_archives.contains(*lang) {
+                let sum = bundle_sums.get(&***lang).ok_or_else(|| {
+                    Error::new(format!(
+                        "the bundle has no sum for 'tldr-pages.{lang}.zip'."
+                    ))
+                    .kind(ErrorKind::Download)
+                })?;
+                Self::extract_bundled_archive(staging, lang, &mut archive, sum, &mut limits)?
+            } else {
+                Self::extract_lang_archive(
+                    Some(staging),
+                    &lang_dir,
+                    &mut archive,
+                    &lang_prefixes[*lang],
+                    &mut limits,
+                )?
+            };
+            downloaded.push((lang.as_str(), n_downloaded));
+        }
+
+        let (counter, changelog, hashes) = self.diff_staged(staging, &downloaded)?;
+        self.swap_in(staging, &imported)?;
+        self.write_page_sums(staging, &imported, hashes)?;
+        changelog.append_to(&self.dir.join(CHANGELOG))?;
+
+        // The imported pages might not match the old sums, so these have to be removed.
+        // Otherwise, the next update could consider the imported languages up to date.
+        let old_sums = fs::read_to_string(self.dir.join(SUMFILE)).unwrap_or_default();
+        let mut sums = String::new();
+
+        for l in old_sums.lines() {
+            if !imported
+                .iter()
+                .any(|lang| l.ends_with(&format!("tldr-pages.{lang}.zip")))
+            {
+                let _ = writeln!(sums, "{l}");
+            }
+        }
+
+        if bundle.is_some() {
+            // Bundles contain the checksum file of the exporting cache, so that the next
+            // update only downloads languages that have changed since the export.
+            // Only sums of archives that were verified above can be trusted.
+            for lang in &bundled_archives {
+                let _ = writeln!(sums, "{}  tldr-pages.{lang}.zip", bundle_sums[&**lang]);
+            }
+        } else if let Some(sum) = sum {
+            // Language archives are verified against the same sums the mirror provides.
+            if let Some((lang, prefix)) = lang_prefixes.first_key_value() {
+                if prefix.is_empty() {
+                    let _ = writeln!(sums, "{sum}  tldr-pages.{lang}.zip");
+                }
+            }
+        }
+
+        self.write_sumfile(staging, &sums, &SumfileHeaders::default())?;
+
+        infoln!(
+            "import successful (total: {} pages, {} new).",
+            Paint::new(counter.all_downloaded).fg(Green).bold(),
+            Paint::new(counter.all_new).fg(Green).bold(),
+        );
+
+        Ok(())
+    }
+
+    /// Install pages from a local archive (`tldr.zip`, `tldr-pages.<lang>.zip` or a bundle
+    /// created by `export`).
+    ///
+    /// If `languages` is specified, only these languages are imported from full archives.
+    /// The language of a language archive is taken from `languages` or the filename.
+    pub fn import(
+        &mut self,
+        cfg: &CacheConfig,
+        archive_path: &Path,
+        languages: Option<&[String]>,
+    ) -> Result<()> {
+        let _lock = CacheLock::acquire(self.dir, LOCK_TIMEOUT)?;
+        self.cleanup_staging()?;
+
+        let staging = self.staging_dir();
+        fs::create_dir_all(&staging)?;
+
+        let result = self
+            .import_staged(&staging, cfg, archive_path, languages)
+            .map_err(|e| {
+                let message = format!("'{}': {e}", archive_path.display());
+                Error::new(message).kind(e.kind)
+            })
+            .and_then(|()| self.write_index(&staging));
+        let cleanup = fs::remove_dir_all(&staging);
+        self.reset();
+        result?;
+
+        Ok(cleanup?)
+    }
+
+    /// Add all pages from `store` to `zip` under `lang_dir`.
+    ///
+    /// Returns the number of added pages.
+    fn add_lang_to_bundle<W>(
+        zip: &mut ZipWriter<W>,
+        store: &dyn PageStore,
+        lang_dir: &str,
+        options: FileOptions,
+    ) -> Result<i32>
+    where
+        W: Write + Seek,
+    {
+        zip.add_directory(lang_dir, options)?;
+        let mut platforms = store.platforms()?;
+        // Sort to make bundles of the same cache identical.
+        platforms.sort_unstable();
+        let mut n_added = 0;
+
+        for platform in platforms {
+            let platform_dir = format!("{lang_dir}/{}", platform.to_string_lossy());
+            zip.add_directory(&platform_dir, options)?;
+
+            let mut pages = store.list(&platform)?;
+            pages.sort_unstable();
+
+            for page in pages {
+                let page = page.to_string_lossy();
+                zip.start_file(format!("{platform_dir}/{page}"), options)?;
+                io::copy(&mut store.open(&platform, &page)?, zip)?;
+                n_added += 1;
+            }
+        }
+
+        Ok(n_added)
+    }
+
+    /// Write all installed languages, the checksum file and a manifest to `bundle_path`.
+    fn export_to(&self, bundle_path: &Path) -> Result<()> {
+        let languages = self.languages()?;
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(bundle_path)?));
+        let options = FileOptions::default();
+        let mut n_total = 0;
+
+        for lang in &languages {
+            let lang_dir = format!("pages.{lang}");
+            // This is safe to unwrap, the language is installed.
+            let store = self.store(&lang_dir)?.unwrap();
+
+            let n = if self.lang_stored_as(&lang_dir, StorageMode::Dir) {
+                Self::add_lang_to_bundle(&mut zip, &*store, &lang_dir, options)?
+            } else {
+                // Archives are added unchanged, so that they can be verified using
+                // the checksum file when the bundle is imported.
+                zip.start_file(
+                    format!("tldr-pages.{lang}.zip"),
+                    options.compression_method(CompressionMethod::Stored),
+                )?;
+                io::copy(&mut File::open(self.lang_archive(&lang_dir))?, &mut zip)?;
+
+                let mut n = 0;
+                for platform in store.platforms()? {
+                    n += i32::try_from(store.list(&platform)?.len()).unwrap_or(i32::MAX);
+                }
+                n
+            };
+            n_total += n;
+
+            infoln!("'{lang_dir}': {} pages", Paint::new(n).fg(Green).bold());
+        }
+
+        let sumfile = self.dir.join(SUMFILE);
+        if sumfile.is_file() {
+            zip.start_file(SUMFILE, options)?;
+            io::copy(&mut File::open(sumfile)?, &mut zip)?;
+        }
+
+        let manifest = BundleManifest {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created: Self::now(),
+            languages,
+        };
+        zip.start_file(BUNDLE_MANIFEST, options)?;
+        // Serializing this struct cannot fail.
+        zip.write_all(toml::to_string(&manifest).unwrap().as_bytes())?;
+
+        zip.finish()?
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+
+        infoln!(
+            "export successful (total: {} pages).",
+            Paint::new(n_total).fg(Green).bold()
+        );
+
+        Ok(())
+    }
+
+    /// Pack the cache into a bundle, which can be installed on another machine using `import`.
+    ///
+    /// The bundle contains every installed language directory, the checksum file and a manifest.
+    /// Languages stored as archives are added unchanged, so that they can be verified on import.
+    pub fn export(&self, bundle_path: &Path) -> Result<()> {
+        if !self.lang_installed(ENGLISH_DIR) {
+            return Err(Error::new(
+                "cache is empty. Run 'tldr --update' before exporting it.",
+            ));
+        }
+
+        // Updates must not replace language directories while they are being packed.
+        let _lock = CacheLock::acquire(self.dir, LOCK_TIMEOUT)?;
+
+        self.export_to(bundle_path).map_err(|e| {
+            // Do not leave a partially written bundle behind.
+            let _ = fs::remove_file(bundle_path);
+            let message = format!("'{}': {e}", bundle_path.display());
+            Error::new(message).kind(e.kind)
+        })
+    }
+
+    /// Compare the files of `lang_dir` with the recorded sums.
+    ///
+    /// Returns a list of problems, as pairs of a description and a path.
+    fn check_lang(
+        &self,
+        lang_dir: &str,
+        recorded: &BTreeMap<String, String>,
+        sums: &HashMap<&str, &str>,
+    ) -> Result<Vec<(&'static str, String)>> {
+        let prefix = format!("{lang_dir}/");
+        let expected: BTreeMap<&str, &str> = recorded
+            .range(prefix.clone()..)
+            .take_while(|(path, _)| path.starts_with(&prefix))
+            .map(|(path, sum)| (&path[prefix.len()..], &**sum))
+            .collect();
+
+        let dir = self.dir.join(lang_dir);
+        let archive = self.lang_archive(lang_dir);
+        let mut problems = vec![];
+
+        if dir.is_dir() {
+            if expected.is_empty() {
+                problems.push(("no sums recorded", prefix));
+                return Ok(problems);
+            }
+
+            let mut files = BTreeSet::new();
+            Self::walk(&dir, "", &mut files)?;
+
+            for file in &files {
+                match expected.get(&**file) {
+                    Some(sum) if Self::hash_file(&dir.join(file))? != *sum => {
+                        problems.push(("modified", format!("{prefix}{file}")));
+                    }
+                    Some(_) => {}
+                    None => problems.push(("unexpected", format!("{prefix}{file}"))),
+                }
+            }
+
+            for file in expected.keys() {
+                if !files.contains(*file) {
+                    problems.push(("missing", format!("{prefix}{file}")));
+                }
+            }
+        } else if archive.is_file() {
+            // Archives are verified using the checksum file.
+            let lang = lang_dir.strip_prefix("pages.").unwrap_or(lang_dir);
+            let name = archive.file_name().unwrap().to_string_lossy().into_owned();
+
+            match sums.get(lang) {
+                Some(sum) if Self::hash_file(&archive)? != *sum => {
+                    problems.push(("modified", name));
+                }
+                Some(_) => {}
+                None => problems.push(("no sums recorded", name)),
+            }
+        } else {
+            for file in expected.keys() {
+                problems.push(("missing", format!("{prefix}{file}")));
+            }
+        }
+
+        Ok(problems)
+    }
+
+    /// Install `languages` again in place of the damaged ones.
+    ///
+    /// Languages are restored from their archive in the cache if it is intact, otherwise they
+    /// are downloaded again. Archives are verified using the installed checksum file,
+    /// so that the cache is restored to the state of the last update.
+    fn repair_staged(&self, staging: &Path, cfg: &CacheConfig, languages: &[String]) -> Result<()> {
+        let sums = fs::read_to_string(self.dir.join(SUMFILE)).unwrap_or_default();
+        let sum_map = Self::parse_sumfile(&sums)?;
+        let mut local = vec![];
+        let mut outdated = BTreeMap::new();
+
+        for lang in languages {
+            let Some(sum) = sum_map.get(&**lang) else {
+                return Err(Error::new(format!(
+                    "'pages.{lang}' was not downloaded from a mirror and cannot be repaired. \
+                    Install it again using --import."
+                )));
+            };
 
-            if fname.ends_with('/') {
-                fs::create_dir_all(&path)?;
-                continue;
+            let archive = self.lang_archive(&format!("pages.{lang}"));
+            if archive.is_file() && Self::hash_file(&archive)? == *sum {
+                local.push(lang);
+            } else {
+                outdated.insert(lang.clone(), (*sum).to_string());
             }
+        }
 
-            let mut file = File::create(&path)?;
-            io::copy(&mut page, &mut file)?;
+        let mut n_restored = 0;
 
-            n_downloaded += 1;
+        for lang in &local {
+            let fname = format!("tldr-pages.{lang}.zip");
+            infoln!("restoring 'pages.{lang}' from '{fname}'...");
+            fs::copy(self.dir.join(&fname), staging.join(&fname))?;
+            n_restored += Self::extract_staged(lang, staging, cfg)?;
         }
 
-        let n_new = n_downloaded - n_existing;
-        *all_downloaded += n_downloaded;
-        *all_new += n_new;
+        let hashes = Self::hash_pages(staging, &local)?;
+        self.swap_in(staging, &local)?;
+        self.write_page_sums(staging, &local, hashes)?;
 
-        info_end!(
-            " {} pages, {} new",
-            Paint::new(n_downloaded).fg(Green).bold(),
-            Paint::new(n_new).fg(Green).bold()
+        if !outdated.is_empty() {
+            n_restored += Self::try_mirrors(staging, cfg, |mirror| {
+                let (counter, _) = self.install_from(mirror, staging, cfg, &outdated)?;
+                Ok(counter.all_downloaded)
+            })
+            .map_err(|e| match e.kind {
+                ErrorKind::Download => e.describe(
+                    "\n\nIf the pages on the mirror have changed, run 'tldr --update' instead.",
+                ),
+                _ => e,
+            })?;
+        }
+
+        infoln!(
+            "cache repair successful ({} pages restored).",
+            Paint::new(n_restored).fg(Green).bold(),
         );
 
         Ok(())
     }
 
-    /// Delete the old cache and replace it with a fresh copy.
-    pub fn update(&self, mirror: &str, languages: &mut Vec<String>) -> Result<()> {
-        // Sort to always download archives in alphabetical order.
+    /// Check the installed pages against the sums recorded when they were extracted, and
+    /// optionally re-download damaged languages.
+    pub fn verify(&mut self, cfg: &CacheConfig, repair: bool) -> Result<()> {
+        if !self.lang_installed(ENGLISH_DIR) {
+            return Err(Error::new(
+                "cache is empty. Run 'tldr --update' to download pages.",
+            ));
+        }
+
+        // Updates must not replace language directories while they are being verified.
+        let _lock = CacheLock::acquire(self.dir, LOCK_TIMEOUT)?;
+
+        let recorded = self.read_page_sums();
+        let sums = fs::read_to_string(self.dir.join(SUMFILE)).unwrap_or_default();
+        let sum_map = Self::parse_sumfile(&sums).unwrap_or_default();
+
+        let mut languages = self.scan_languages()?;
+        // Languages that are recorded but not installed anymore have been deleted.
+        languages.extend(recorded.keys().filter_map(|path| {
+            let (lang_dir, _) = path.split_once('/')?;
+            Some(lang_dir.strip_prefix("pages.")?.to_string())
+        }));
         languages.sort_unstable();
-        // The user can put duplicates in the config file.
         languages.dedup();
 
-        let archives = self.download_and_verify(mirror, languages)?;
+        let mut damaged = vec![];
+        let mut stdout = io::stdout().lock();
 
-        if archives.is_empty() {
-            infoln!(
-                "there is nothing to do. Run 'tldr --clean-cache' if you want to force an update."
-            );
+        for lang in languages {
+            let lang_dir = format!("pages.{lang}");
+            let problems = self.check_lang(&lang_dir, &recorded, &sum_map)?;
+
+            if problems.is_empty() {
+                writeln!(stdout, "{lang_dir}: {}", Paint::new("OK").fg(Green).bold())?;
+                continue;
+            }
+
+            writeln!(
+                stdout,
+                "{lang_dir}: {}",
+                Paint::new(format!("{} problem(s)", problems.len()))
+                    .fg(Red)
+                    .bold()
+            )?;
+            for (problem, path) in problems {
+                writeln!(stdout, "  {problem}: {path}")?;
+            }
+
+            damaged.push(lang);
+        }
+
+        drop(stdout);
+
+        if damaged.is_empty() {
             return Ok(());
         }
+        if !repair {
+            return Err(Error::new(
+                "the cache is damaged. Run 'tldr --verify-cache --repair' to repair it.",
+            ));
+        }
+
+        self.cleanup_staging()?;
+        let staging = self.staging_dir();
+        fs::create_dir_all(&staging)?;
 
-        let mut all_downloaded = 0;
-        let mut all_new = 0;
+        let result = self
+            .repair_staged(&staging, cfg, &damaged)
+            .and_then(|()| self.write_index(&staging));
+        let cleanup = fs::remove_dir_all(&staging);
+        self.reset();
+        result?;
+
+        Ok(cleanup?)
+    }
+
+    /// Print pages that were added, removed or changed by updates since `since` (seconds
+    /// since the Unix epoch), grouped by the date of the update and language.
+    pub fn whats_new(&self, since: Option<u64>) -> Result<()> {
+        let changelog = Changelog::load(&self.dir.join(CHANGELOG))?;
+        let mut stdout = io::stdout().lock();
+        let mut group = None;
 
-        for (lang_dir, mut archive) in archives {
-            // `list_all_vec` can fail when `pages.en` is empty, hence the default of 0.
-            #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
-            let n_existing = self.list_all_vec(&lang_dir).map(|v| v.len()).unwrap_or(0) as i32;
+        for change in changelog.since(since.unwrap_or(0)) {
+            let date = util::date_fmt(change.time);
 
-            let lang_dir_full = self.dir.join(&lang_dir);
-            if lang_dir_full.is_dir() {
-                fs::remove_dir_all(&lang_dir_full)?;
+            if group.as_ref() != Some(&(change.time, &change.lang)) {
+                if group.is_some() {
+                    writeln!(stdout)?;
+                }
+                writeln!(
+                    stdout,
+                    "{} ({}):",
+                    Paint::new(date).bold(),
+                    Paint::new(format!("pages.{}", change.lang)).fg(Cyan).bold()
+                )?;
+                group = Some((change.time, &change.lang));
             }
 
-            self.extract_lang_archive(
-                &lang_dir,
-                &mut archive,
-                n_existing,
-                &mut all_downloaded,
-                &mut all_new,
+            let color = match change.kind {
+                ChangeKind::Added => Green,
+                ChangeKind::Removed => Red,
+                ChangeKind::Changed => Yellow,
+            };
+
+            writeln!(
+                stdout,
+                "  {:7} {}/{}",
+                Paint::new(change.kind).fg(color),
+                change.platform,
+                change.page
             )?;
         }
 
-        infoln!(
-            "cache update successful (total: {} pages, {} new).",
-            Paint::new(all_downloaded).fg(Green).bold(),
-            Paint::new(all_new).fg(Green).bold(),
-        );
+        if group.is_none() {
+            drop(stdout);
+            infoln!("no changes have been recorded. Changes are recorded when pages are updated.");
+        }
 
         Ok(())
     }
@@ -251,9 +1966,28 @@ impl<'a> Cache<'a> {
             return Ok(());
         }
 
+        let _lock = CacheLock::acquire(self.dir, LOCK_TIMEOUT)?;
+
         infoln!("cleaning the cache directory...");
-        fs::remove_dir_all(self.dir)?;
-        fs::create_dir_all(self.dir)?;
+        for entry in fs::read_dir(self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            // The lock file must stay in place while the lock is held.
+            if path == CacheLock::path(self.dir) {
+                continue;
+            }
+            // Custom pages are never removed, even if they are inside the cache directory.
+            if self.custom_dirs.iter().any(|dir| dir.starts_with(&path)) {
+                continue;
+            }
+
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(path)?;
+            } else {
+                fs::remove_file(path)?;
+            }
+        }
 
         Ok(())
     }
@@ -262,26 +1996,30 @@ impl<'a> Cache<'a> {
     fn get_platforms(&self) -> Result<&[OsString]> {
         self.platforms
             .get_or_try_init(|| {
-                let mut result = vec![];
-
-                for entry in fs::read_dir(self.dir.join(ENGLISH_DIR))? {
-                    let entry = entry?;
-                    let path = entry.path();
-                    let platform = path.file_name().unwrap();
-
-                    result.push(platform.to_os_string());
-                }
+                let mut result = if let Some(index) = self.index() {
+                    index.platforms("en")
+                } else if let Some(store) = self.store(ENGLISH_DIR)? {
+                    store.platforms()?
+                } else {
+                    vec![]
+                };
 
                 if result.is_empty() {
-                    Err(Error::new(
+                    return Err(Error::new(
                         "'pages.en' contains no platform directories. Please run 'tldr --update'.",
-                    ))
-                } else {
-                    // read_dir() order can differ across runs, so it's
-                    // better to sort the Vec for consistency.
-                    result.sort_unstable();
-                    Ok(result)
+                    ));
                 }
+
+                // Custom pages can be written for platforms that do not exist upstream.
+                for (_, store) in self.custom_stores() {
+                    result.append(&mut store.platforms()?);
+                }
+
+                // read_dir() order can differ across runs, so it's
+                // better to sort the Vec for consistency.
+                result.sort_unstable();
+                result.dedup();
+                Ok(result)
             })
             .map(Vec::as_slice)
     }
@@ -301,93 +2039,303 @@ impl<'a> Cache<'a> {
         }
     }
 
-    /// Find a page for the given platform.
-    fn find_page_for<P>(&self, fname: &str, platform: P, lang_dirs: &[String]) -> Option<PathBuf>
+    /// Find a page for the given platform. Custom pages take precedence over the cache.
+    fn find_page_for<P>(
+        &self,
+        fname: &str,
+        platform: P,
+        lang_dirs: &[String],
+    ) -> Result<Option<PageRef>>
     where
-        P: AsRef<Path>,
+        P: AsRef<OsStr>,
     {
-        for lang_dir in lang_dirs {
-            let path = self.dir.join(lang_dir).join(&platform).join(fname);
+        let platform = platform.as_ref();
+        let page = |lang: Option<&str>, source| PageRef {
+            lang: lang.map(str::to_string),
+            platform: platform.to_string_lossy().into_owned(),
+            name: fname.strip_suffix(".md").unwrap_or(fname).to_string(),
+            source,
+        };
+
+        for (dir, store) in self.custom_stores() {
+            if !fname.ends_with(PATCH_SUFFIX) && store.contains(platform, fname) {
+                return Ok(Some(page(None, PageSource::Custom(dir.to_path_buf()))));
+            }
+        }
 
-            if path.is_file() {
-                return Some(path);
+        for lang_dir in lang_dirs {
+            if self.page_exists(lang_dir, platform, fname)? {
+                let lang = lang_dir.strip_prefix("pages.").unwrap_or(lang_dir);
+                return Ok(Some(page(Some(lang), PageSource::Cache)));
             }
         }
 
-        None
+        Ok(None)
     }
 
-    /// Find all pages with the given name.
-    pub fn find(&self, name: &str, languages: &[String], platform: &str) -> Result<Vec<PathBuf>> {
-        // https://github.com/tldr-pages/tldr/blob/main/CLIENT-SPECIFICATION.md#page-resolution
+    /// Check if `platform/fname` is installed in `lang_dir`.
+    ///
+    /// This does not use the index, checking a single page is cheaper than reading it.
+    fn page_exists(&self, lang_dir: &str, platform: &OsStr, fname: &str) -> Result<bool> {
+        Ok(match self.store(lang_dir)? {
+            Some(store) => store.contains(platform, fname),
+            None => false,
+        })
+    }
 
-        let platforms = self.get_platforms_and_check(platform)?;
-        let file = format!("{name}.md");
+    /// Open a page returned by `find`.
+    pub fn open_page(&self, page: &PageRef) -> Result<Box<dyn Read>> {
+        let fname = format!("{}.md", page.name);
+
+        let reader = match (&page.source, &page.lang) {
+            (PageSource::Custom(dir), _) => File::open(dir.join(&page.platform).join(&fname))
+                .map(|f| Box::new(f) as Box<dyn Read>)
+                .map_err(Error::from),
+            (PageSource::Cache, Some(lang)) => match self.store(&format!("pages.{lang}"))? {
+                Some(store) => store.open(OsStr::new(&page.platform), &fname),
+                None => Err(Error::new("the language is not installed")),
+            },
+            (PageSource::Cache, None) => Err(Error::new("the page has no language")),
+        };
+
+        reader.map_err(|e| Error::new(format!("'{page}': {e}")).kind(ErrorKind::Io))
+    }
+
+    /// Find patches (`<platform>/<page>.patch.md`) for `page` in the custom page directories.
+    /// Patches for the platform of the page come before the ones in `common`.
+    pub fn find_patches(&self, page: &PageRef) -> Vec<PathBuf> {
+        let fname = format!("{}{PATCH_SUFFIX}", page.name);
+        let mut platforms = vec![&*page.platform];
+        if page.platform != "common" {
+            platforms.push("common");
+        }
 
         let mut result = vec![];
-        let mut lang_dirs: Vec<String> = languages.iter().map(|x| format!("pages.{x}")).collect();
-        // We can't sort here - order is defined by the user.
-        lang_dirs.dedup_nosort();
 
-        // `common` is always searched, so we skip the search for the specified platform
-        // if the user has requested only `common` (to prevent searching twice)
-        if platform != "common" {
-            if let Some(path) = self.find_page_for(&file, platform, &lang_dirs) {
-                result.push(path);
+        for platform in platforms {
+            for dir in self.custom_dirs {
+                let patch_path = dir.join(platform).join(&fname);
+                if patch_path.is_file() {
+                    result.push(patch_path);
+                }
             }
         }
 
-        // Fall back to `common` if the page is not found in `platform`.
-        if let Some(path) = self.find_page_for(&file, "common", &lang_dirs) {
-            result.push(path);
+        result
+    }
+
+    /// Find names of pages similar to `name` on all platforms, in English and custom pages.
+    ///
+    /// Names are ranked by their edit distance to `name`. A name can also match by a prefix,
+    /// in which case it is ranked slightly lower (e.g. `dockr` suggests `docker`, then
+    /// `docker-compose`).
+    ///
+    /// Pages that cannot be listed are not suggested, so that errors do not replace
+    /// the error for the page that was not found.
+    pub fn suggest(&self, name: &str) -> Vec<String> {
+        self.try_suggest(name).unwrap_or_default()
+    }
+
+    /// Find names of pages similar to `name` (see `suggest`).
+    fn try_suggest(&self, name: &str) -> Result<Vec<String>> {
+        let mut pages = self.list_all_vec(ENGLISH_DIR)?;
+        for (_, store) in self.custom_stores() {
+            pages.append(&mut Self::list_custom(&store)?);
         }
 
-        // Fall back to all other platforms if the page is not found in `platform`.
-        for alt_platform in platforms {
-            // `platform` and `common` were already searched, so we can skip them here.
-            if alt_platform == platform || alt_platform == "common" {
+        let len = name.chars().count();
+        // Allow about one typo for every three characters.
+        let max_score = len / 3 + 1;
+        let mut scored = vec![];
+
+        for page in &pages {
+            let page = page.to_string_lossy();
+            let Some(candidate) = page.strip_suffix(".md") else {
                 continue;
+            };
+
+            let full = util::edit_distance(name, candidate);
+            // Compare with prefixes that are a character shorter or longer than `name`,
+            // to also allow a missing or an extra character.
+            let prefix = (len.saturating_sub(1)..=len + 1)
+                .filter_map(|n| {
+                    candidate
+                        .char_indices()
+                        .nth(n)
+                        .map(|(i, _)| &candidate[..i])
+                })
+                .map(|prefix| util::edit_distance(name, prefix) + 1)
+                .min()
+                .unwrap_or(usize::MAX);
+            let score = full.min(prefix);
+
+            if score <= max_score {
+                scored.push((score, candidate.to_string()));
             }
+        }
 
-            if let Some(path) = self.find_page_for(&file, alt_platform, &lang_dirs) {
-                if result.is_empty() {
-                    let alt_platform = alt_platform.to_string_lossy();
+        scored.sort_unstable();
+        scored.dedup_by(|a, b| a.1 == b.1);
+
+        Ok(scored
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, name)| name)
+            .collect())
+    }
+
+    /// Find all pages with the given name.
+    ///
+    /// `platforms` are searched in order, followed by `common` (if it is not in `platforms`)
+    /// and all other platforms.
+    pub fn find(
+        &self,
+        name: &str,
+        languages: &[String],
+        platforms: &[String],
+    ) -> Result<Vec<PageRef>> {
+        // https://github.com/tldr-pages/tldr/blob/main/CLIENT-SPECIFICATION.md#page-resolution
+
+        for platform in platforms {
+            self.get_platforms_and_check(platform)?;
+        }
+
+        let file = format!("{name}.md");
+
+        let mut result = vec![];
+        // We can't sort here - order is defined by the user.
+        let lang_dirs: Vec<String> = self
+            .language_chain(languages)?
+            .iter()
+            .map(|x| format!("pages.{x}"))
+            .collect();
+
+        let mut order: Vec<&OsStr> = platforms.iter().map(OsStr::new).collect();
+        // Fall back to `common` if the page is not found in `platforms`.
+        if !order.contains(&OsStr::new("common")) {
+            order.push(OsStr::new("common"));
+        }
+        let n_requested = order.len();
 
-                    if platform == "common" {
-                        warnln!(
-                            "showing page from platform '{alt_platform}', \
-                            because '{name}' does not exist in 'common'"
-                        );
+        // Fall back to all other platforms if the page is not found in `common`.
+        for alt_platform in self.get_platforms()? {
+            if !order.contains(&alt_platform.as_os_str()) {
+                order.push(alt_platform);
+            }
+        }
+
+        for (i, platform) in order.iter().enumerate() {
+            if let Some(page) = self.find_page_for(&file, platform, &lang_dirs)? {
+                if result.is_empty() && i >= n_requested {
+                    let mut searched: Vec<String> = order[..n_requested]
+                        .iter()
+                        .map(|x| format!("'{}'", x.to_string_lossy()))
+                        .collect();
+                    // `common` is always searched, so this is safe to unwrap.
+                    let last = searched.pop().unwrap();
+                    let searched = if searched.is_empty() {
+                        last
                     } else {
-                        warnln!(
-                            "showing page from platform '{alt_platform}', \
-                            because '{name}' does not exist in '{platform}' and 'common'"
-                        );
-                    }
+                        format!("{} and {last}", searched.join(", "))
+                    };
+
+                    warnln!(
+                        "showing page from platform '{}', because '{name}' does not exist in \
+                        {searched}",
+                        platform.to_string_lossy()
+                    );
                 }
 
-                result.push(path);
+                result.push(page);
             }
         }
 
         Ok(result)
     }
 
-    /// List all available pages in `lang` for `platform`.
-    fn list_dir<P, Q>(&self, platform: P, lang_dir: Q) -> Result<Vec<OsString>>
+    /// If `page` is an alias of another page, return the name of that page.
+    ///
+    /// Alias pages have a single example, which shows the original command's page
+    /// (e.g. `tldr gh codespace`). This does not depend on the language of the page.
+    fn alias_target(&self, page: &PageRef) -> Result<Option<String>> {
+        let mut contents = vec![];
+        self.open_page(page)?.read_to_end(&mut contents)?;
+        let contents = String::from_utf8_lossy(&contents);
+
+        let mut examples = contents.lines().filter(|l| l.starts_with('`'));
+        let (Some(example), None) = (examples.next(), examples.next()) else {
+            return Ok(None);
+        };
+        let Some(args) = example
+            .trim_end()
+            .strip_prefix("`tldr ")
+            .and_then(|s| s.strip_suffix('`'))
+        else {
+            return Ok(None);
+        };
+
+        let words: Vec<&str> = args.split_whitespace().collect();
+        // Options and placeholders mean that this is a regular example.
+        if words.is_empty() || words.iter().any(|w| w.starts_with('-') || w.contains("{{")) {
+            return Ok(None);
+        }
+
+        Ok(Some(words.join("-").to_lowercase()))
+    }
+
+    /// Find all pages with the given name like `find`. If the first page is an alias of
+    /// another page, the pages of the original command are returned instead.
+    pub fn find_following_aliases(
+        &self,
+        name: &str,
+        languages: &[String],
+        platforms: &[String],
+    ) -> Result<Vec<PageRef>> {
+        let mut pages = self.find(name, languages, platforms)?;
+        let mut seen = vec![name.to_string()];
+
+        while let Some(first) = pages.first() {
+            let Some(target) = self.alias_target(first)? else {
+                break;
+            };
+
+            let alias = seen.last().unwrap();
+            if seen.contains(&target) {
+                warnln!(
+                    "'{alias}' is an alias of '{target}', which forms a cycle. Showing '{alias}'."
+                );
+                break;
+            }
+
+            let target_pages = self.find(&target, languages, platforms)?;
+            if target_pages.is_empty() {
+                warnln!(
+                    "'{alias}' is an alias of '{target}', which does not exist. Showing '{alias}'."
+                );
+                break;
+            }
+
+            infoln!("'{alias}' is an alias of '{target}', showing '{target}'.");
+            seen.push(target);
+            pages = target_pages;
+        }
+
+        Ok(pages)
+    }
+
+    /// List all available pages in `lang_dir` for `platform`.
+    fn list_dir<P>(&self, platform: P, lang_dir: &str) -> Result<Vec<OsString>>
     where
-        P: AsRef<Path>,
-        Q: AsRef<Path>,
+        P: AsRef<OsStr>,
     {
-        match fs::read_dir(self.dir.join(lang_dir.as_ref()).join(platform)) {
-            Ok(entries) => {
-                let entries = entries.map(|res| res.map(|ent| ent.file_name()));
-                Ok(entries.collect::<io::Result<Vec<OsString>>>()?)
-            }
-            // If the directory does not exist, return an empty Vec instead of an error
-            // (some platform directories do not exist in some translations).
-            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
-            Err(e) => Err(e.into()),
+        if let Some(index) = self.index() {
+            let lang = lang_dir.strip_prefix("pages.").unwrap_or(lang_dir);
+            return index.list(lang, &platform.as_ref().to_string_lossy());
+        }
+
+        match self.store(lang_dir)? {
+            Some(store) => store.list(platform.as_ref()),
+            None => Ok(vec![]),
         }
     }
 
@@ -410,40 +2358,199 @@ impl<'a> Cache<'a> {
         Ok(stdout.flush()?)
     }
 
-    /// List all pages in English for `platform` and common.
-    pub fn list_for(&self, platform: &str) -> Result<()> {
-        // This is here just to check if the platform exists.
-        self.get_platforms_and_check(platform)?;
+    /// List all pages in English for `platforms` and common.
+    pub fn list_for(&self, platforms: &[String]) -> Result<()> {
+        let mut platforms: Vec<&str> = platforms.iter().map(String::as_str).collect();
+        platforms.push("common");
+        platforms.dedup_nosort();
 
-        let pages = if platform == "common" {
-            self.list_dir(platform, ENGLISH_DIR)?
-        } else {
-            self.list_dir(platform, ENGLISH_DIR)?
-                .into_iter()
-                .chain(self.list_dir("common", ENGLISH_DIR)?)
-                .collect()
-        };
+        let mut pages = vec![];
+
+        for platform in platforms {
+            // This is here just to check if the platform exists.
+            self.get_platforms_and_check(platform)?;
+
+            pages.append(&mut self.list_dir(platform, ENGLISH_DIR)?);
+            for (_, store) in self.custom_stores() {
+                pages.append(&mut Self::list_custom_for(&store, platform.as_ref())?);
+            }
+        }
 
         Self::print_basenames(pages)
     }
 
-    /// List all pages in `lang` and return a `Vec`.
-    fn list_all_vec<S>(&self, lang_dir: S) -> Result<Vec<OsString>>
-    where
-        S: AsRef<Path>,
-    {
+    /// List all languages and platforms in which the page `name` exists.
+    pub fn list_translations(&self, name: &str) -> Result<()> {
+        let fname = format!("{name}.md");
+        let mut found = vec![];
+
+        let mut custom = vec![];
+        for (_, store) in self.custom_stores() {
+            for platform in store.platforms()? {
+                if store.contains(&platform, &fname) {
+                    custom.push(platform.to_string_lossy().into_owned());
+                }
+            }
+        }
+        if !custom.is_empty() {
+            // Multiple directories can have pages for the same platform.
+            custom.sort_unstable();
+            custom.dedup();
+            found.push(("custom".to_string(), custom));
+        }
+
+        for lang in self.languages()? {
+            let lang_dir = format!("pages.{lang}");
+            let mut platforms = vec![];
+
+            for platform in self.get_platforms()? {
+                if self.page_exists(&lang_dir, platform, &fname)? {
+                    platforms.push(platform.to_string_lossy().into_owned());
+                }
+            }
+
+            if !platforms.is_empty() {
+                found.push((lang, platforms));
+            }
+        }
+
+        if found.is_empty() {
+            return Err(Error::page_not_found(&self.suggest(name)));
+        }
+
+        let mut stdout = BufWriter::new(io::stdout().lock());
+
+        for (lang, platforms) in found {
+            // Language codes are at most 5 characters (ll_CC), but "custom" has 6.
+            writeln!(stdout, "{lang:6} : {}", platforms.join(", "))?;
+        }
+
+        Ok(stdout.flush()?)
+    }
+
+    /// List all pages in `lang_dir` and return a `Vec`.
+    fn list_all_vec(&self, lang_dir: &str) -> Result<Vec<OsString>> {
         let mut result = vec![];
 
         for platform in self.get_platforms()? {
-            result.append(&mut self.list_dir(platform, &lang_dir)?);
+            result.append(&mut self.list_dir(platform, lang_dir)?);
+        }
+
+        Ok(result)
+    }
+
+    /// List custom pages in `store` for `platform`. Patches are not pages, so they are skipped.
+    fn list_custom_for(store: &DirStore, platform: &OsStr) -> Result<Vec<OsString>> {
+        let mut pages = store.list(platform)?;
+        pages.retain(|fname| !fname.to_string_lossy().ends_with(PATCH_SUFFIX));
+        Ok(pages)
+    }
+
+    /// List all custom pages in `store`.
+    fn list_custom(store: &DirStore) -> Result<Vec<OsString>> {
+        let mut result = vec![];
+
+        for platform in store.platforms()? {
+            result.append(&mut Self::list_custom_for(store, &platform)?);
         }
 
         Ok(result)
     }
 
-    /// List all pages in English.
+    /// List all pages in English and all custom pages.
     pub fn list_all(&self) -> Result<()> {
-        Self::print_basenames(self.list_all_vec(ENGLISH_DIR)?)
+        let mut pages = self.list_all_vec(ENGLISH_DIR)?;
+
+        for (_, store) in self.custom_stores() {
+            pages.append(&mut Self::list_custom(&store)?);
+        }
+
+        Self::print_basenames(pages)
+    }
+
+    /// Search all installed and custom pages for `terms` and print the best matching line
+    /// of every page that contains all of them, best matches first.
+    pub fn search(&self, terms: &[String]) -> Result<()> {
+        let terms: Vec<String> = terms
+            .iter()
+            .flat_map(|term| term.split_whitespace())
+            .map(str::to_lowercase)
+            .collect();
+
+        let mut candidates: Vec<(String, Rc<dyn PageStore>, OsString, OsString)> = vec![];
+        for (_, store) in self.custom_stores() {
+            let store: Rc<dyn PageStore> = Rc::new(store);
+            for platform in store.platforms()? {
+                for fname in store.list(&platform)? {
+                    candidates.push(("custom".to_string(), store.clone(), platform.clone(), fname));
+                }
+            }
+        }
+        for lang in self.languages()? {
+            let lang_dir = format!("pages.{lang}");
+            let Some(store) = self.store(&lang_dir)? else {
+                continue;
+            };
+            // Installed pages are listed using the index, so only the pages are read.
+            let platforms = match self.index() {
+                Some(index) => index.platforms(&lang),
+                None => store.platforms()?,
+            };
+
+            for platform in platforms {
+                for fname in self.list_dir(&platform, &lang_dir)? {
+                    candidates.push((lang.clone(), store.clone(), platform.clone(), fname));
+                }
+            }
+        }
+
+        let mut results = vec![];
+
+        for (source, store, platform, fname) in candidates {
+            let fname = fname.to_string_lossy();
+            let Some(name) = fname.strip_suffix(".md") else {
+                continue;
+            };
+            if fname.ends_with(PATCH_SUFFIX) {
+                continue;
+            }
+
+            let mut contents = vec![];
+            store.open(&platform, &fname)?.read_to_end(&mut contents)?;
+
+            if let Some(hit) = Hit::find(&String::from_utf8_lossy(&contents), &terms) {
+                let platform = platform.to_string_lossy().into_owned();
+                results.push((hit, name.to_string(), platform, source));
+            }
+        }
+
+        if results.is_empty() {
+            return Err(Error::new(format!(
+                "no pages matching '{}' found.",
+                terms.join(" ")
+            )));
+        }
+
+        // The sort is stable, so custom pages and preferred languages come first among
+        // equally good matches.
+        results.sort_by(|(a, a_name, ..), (b, b_name, ..)| {
+            (b.kind, b.n_terms)
+                .cmp(&(a.kind, a.n_terms))
+                .then_with(|| a_name.cmp(b_name))
+        });
+
+        let mut stdout = BufWriter::new(io::stdout().lock());
+
+        for (hit, name, platform, source) in results {
+            writeln!(
+                stdout,
+                "{} ({platform}, {source}): {}",
+                Paint::new(name).bold(),
+                hit.highlighted(&terms)
+            )?;
+        }
+
+        Ok(stdout.flush()?)
     }
 
     /// List platforms (used in shell completions).
@@ -455,15 +2562,9 @@ impl<'a> Cache<'a> {
 
     /// List languages (used in shell completions).
     pub fn list_languages(&self) -> Result<()> {
-        let languages = fs::read_dir(self.dir)?
-            .filter(|res| res.is_ok() && res.as_ref().unwrap().path().is_dir())
-            .map(|res| res.unwrap().file_name());
         let mut stdout = io::stdout().lock();
 
-        for lang in languages {
-            let lang = lang.to_string_lossy();
-            let lang = lang.strip_prefix("pages.").unwrap_or(&lang);
-
+        for lang in self.languages()? {
             writeln!(stdout, "{lang}")?;
         }
 
@@ -471,23 +2572,24 @@ impl<'a> Cache<'a> {
     }
 
     /// Show cache information.
-    pub fn info(&self, cfg: &Config) -> Result<()> {
+    ///
+    /// `languages` are the languages used to find pages, which are shown along with
+    /// where they come from.
+    pub fn info(
+        &self,
+        cfg: &Config,
+        platform: &DetectedPlatform,
+        languages: &[String],
+        languages_are_from_cli: bool,
+    ) -> Result<()> {
         let mut n_map = BTreeMap::new();
         let mut n_total = 0;
 
-        for lang_dir in fs::read_dir(self.dir)? {
-            let lang_dir = lang_dir?;
-            if !lang_dir.path().is_dir() {
-                continue;
-            }
-            let lang_dir = lang_dir.file_name();
-            let n = self.list_all_vec(&lang_dir)?.len();
-
-            let lang = lang_dir.to_string_lossy();
-            let lang = lang.strip_prefix("pages.").unwrap_or(&lang);
+        for lang in self.languages()? {
+            let n = self.list_all_vec(&format!("pages.{lang}"))?.len();
 
-            n_map.insert(lang.to_string(), n);
             n_total += n;
+            n_map.insert(lang, n);
         }
 
         let mut stdout = io::stdout().lock();
@@ -512,6 +2614,57 @@ impl<'a> Cache<'a> {
             writeln!(stdout, "Automatic updates are disabled")?;
         }
 
+        writeln!(
+            stdout,
+            "Detected platform: {} ({})",
+            Paint::new(platform.platforms().join(", ")).fg(Green).bold(),
+            platform.source
+        )?;
+
+        if !cfg.cache.platform_order.is_empty() {
+            writeln!(
+                stdout,
+                "Platform order (from the config): {}",
+                Paint::new(cfg.cache.platform_order.join(", "))
+                    .fg(Green)
+                    .bold()
+            )?;
+        }
+
+        writeln!(stdout, "Page languages (in order):")?;
+
+        let env_languages = if cfg.cache.languages_from_env && !languages_are_from_cli {
+            util::languages_from_env()
+        } else {
+            vec![]
+        };
+
+        for lang in self.language_chain(languages)? {
+            let source = if languages_are_from_cli {
+                "--language".to_string()
+            } else if let Some((_, var)) = env_languages.iter().find(|(l, _)| *l == lang) {
+                format!("from {var}")
+            } else if !languages.contains(&lang) {
+                let base = lang.split('_').next().unwrap_or_default();
+                format!("regional variant of {base}")
+            } else if lang == "en" && !languages[..languages.len() - 1].contains(&lang) {
+                // English is always added after the languages from the config or environment.
+                "English fallback".to_string()
+            } else {
+                "from the config".to_string()
+            };
+
+            if self.lang_installed(&format!("pages.{lang}")) {
+                writeln!(stdout, "{lang:5} : {source}")?;
+            } else {
+                writeln!(
+                    stdout,
+                    "{lang:5} : {source} {}",
+                    Paint::new("(not installed)").dimmed()
+                )?;
+            }
+        }
+
         writeln!(stdout, "Installed languages:")?;
 
         for (lang, n) in n_map {
@@ -529,6 +2682,25 @@ impl<'a> Cache<'a> {
             Paint::new(n_total).fg(Green).bold(),
         )?;
 
+        if !self.custom_dirs.is_empty() {
+            writeln!(stdout, "Custom pages:")?;
+        }
+
+        for dir in self.custom_dirs {
+            let dir_name = Paint::new(dir.display()).fg(Red);
+
+            if dir.is_dir() {
+                let n = Self::list_custom(&DirStore::new(dir))?.len();
+                writeln!(
+                    stdout,
+                    "{dir_name} : {} pages",
+                    Paint::new(n).fg(Green).bold()
+                )?;
+            } else {
+                writeln!(stdout, "{dir_name} : does not exist")?;
+            }
+        }
+
         Ok(())
     }
 
@@ -536,7 +2708,7 @@ impl<'a> Cache<'a> {
     pub fn age(&self) -> Result<Duration> {
         self.age
             .get_or_try_init(|| {
-                let sumfile = self.dir.join("tldr.sha256sums");
+                let sumfile = self.dir.join(SUMFILE);
                 let metadata = if sumfile.is_file() {
                     fs::metadata(&sumfile)
                 } else {
@@ -555,3 +2727,42 @@ impl<'a> Cache<'a> {
             .copied()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sumfile_headers_round_trip() {
+        let s = "etag http://127.0.0.1:8080: \"abc\"\n\
+            last-modified http://127.0.0.1:8080: Wed, 21 Oct 2015 07:28:00 GMT\n\
+            etag https://example.com/assets: W/\"xyz\"\n\
+            signed-by: 0123abcd\n\
+            languages: de en pt_BR\n";
+        let headers = SumfileHeaders::parse(s);
+
+        let local = &headers.validators["http://127.0.0.1:8080"];
+        assert_eq!(local.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(
+            local.last_modified.as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+        let remote = &headers.validators["https://example.com/assets"];
+        assert_eq!(remote.etag.as_deref(), Some("W/\"xyz\""));
+        assert_eq!(remote.last_modified, None);
+        assert_eq!(headers.signed_by.as_deref(), Some("0123abcd"));
+        assert_eq!(headers.languages, ["de", "en", "pt_BR"]);
+
+        assert_eq!(headers.to_string(), s);
+    }
+
+    #[test]
+    fn sumfile_headers_empty() {
+        let headers = SumfileHeaders::parse("");
+
+        assert!(headers.validators.is_empty());
+        assert_eq!(headers.signed_by, None);
+        assert!(headers.languages.is_empty());
+        assert_eq!(headers.to_string(), "languages: \n");
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::store::PageStore;
+use crate::util::Sha256Writer;
+
+/// The maximum number of changes kept in the changelog. The oldest ones are removed first.
+const MAX_CHANGES: usize = 10_000;
+
+/// SHA256 sums of pages, keyed by platform and page name.
+pub type PageSums = BTreeMap<(String, String), String>;
+
+/// What happened to a page during an update.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl ChangeKind {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "added" => Some(Self::Added),
+            "removed" => Some(Self::Removed),
+            "changed" => Some(Self::Changed),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Changed => "changed",
+        })
+    }
+}
+
+/// A page that was added, removed or changed.
+pub struct Change {
+    /// Time of the update (seconds since the Unix epoch).
+    pub time: u64,
+    pub kind: ChangeKind,
+    pub lang: String,
+    pub platform: String,
+    /// Page name without the `.md` extension.
+    pub page: String,
+}
+
+/// Changes of pages made by updates, kept in the cache across runs.
+///
+/// The file consists of one line for every change:
+/// `<time>\t<kind>\t<lang>\t<platform>\t<page>`.
+#[derive(Default)]
+pub struct Changelog {
+    changes: Vec<Change>,
+}
+
+impl Changelog {
+    /// Load the changelog from `path`. A missing file is treated as an empty changelog.
+    pub fn load(path: &Path) -> Result<Self> {
+        let s = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let changes = s
+            .lines()
+            .filter_map(|l| {
+                let mut spl = l.splitn(5, '\t');
+
+                Some(Change {
+                    time: spl.next()?.parse().ok()?,
+                    kind: ChangeKind::parse(spl.next()?)?,
+                    lang: spl.next()?.to_string(),
+                    platform: spl.next()?.to_string(),
+                    page: spl.next()?.to_string(),
+                })
+            })
+            .collect();
+
+        Ok(Self { changes })
+    }
+
+    /// Calculate the SHA256 sums of all pages in `store`.
+    pub fn hash_store(store: &dyn PageStore) -> Result<PageSums> {
+        let mut hashes = BTreeMap::new();
+
+        for platform in store.platforms()? {
+            for fname in store.list(&platform)? {
+                let fname = fname.to_string_lossy();
+                let Some(name) = fname.strip_suffix(".md") else {
+                    continue;
+                };
+
+                let mut writer = Sha256Writer::new(io::sink());
+                io::copy(&mut store.open(&platform, &fname)?, &mut writer)?;
+
+                let key = (platform.to_string_lossy().into_owned(), name.to_string());
+                hashes.insert(key, writer.finish().1);
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    /// Compare the sums of pages of `lang` in `old` and `new` and add the differences
+    /// to the changelog.
+    ///
+    /// Returns the number of added pages.
+    pub fn diff(&mut self, time: u64, lang: &str, old: &PageSums, new: &PageSums) -> i32 {
+        let start = self.changes.len();
+        let mut n_added = 0;
+
+        let mut push = |kind, (platform, page): &(String, String)| {
+            self.changes.push(Change {
+                time,
+                kind,
+                lang: lang.to_string(),
+                platform: platform.clone(),
+                page: page.clone(),
+            });
+        };
+
+        for (key, sum) in new {
+            match old.get(key) {
+                None => {
+                    push(ChangeKind::Added, key);
+                    n_added += 1;
+                }
+                Some(old_sum) if old_sum != sum => push(ChangeKind::Changed, key),
+                Some(_) => {}
+            }
+        }
+
+        for key in old.keys() {
+            if !new.contains_key(key) {
+                push(ChangeKind::Removed, key);
+            }
+        }
+
+        // Group the changes by kind.
+        self.changes[start..].sort_by_key(|c| c.kind);
+
+        n_added
+    }
+
+    /// Append the changes to the file at `path`, keeping at most `MAX_CHANGES` changes in it.
+    pub fn append_to(self, path: &Path) -> Result<()> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+
+        let mut all = Self::load(path)?;
+        all.changes.extend(self.changes);
+        let excess = all.changes.len().saturating_sub(MAX_CHANGES);
+        all.changes.drain(..excess);
+
+        // Write to a temporary file first, so that an interrupted write cannot lose old changes.
+        let mut tmp = OsString::from(path);
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, all.to_string())?;
+
+        Ok(fs::rename(tmp, path)?)
+    }
+
+    /// Get the changes made at or after `time`.
+    pub fn since(&self, time: u64) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(move |c| c.time >= time)
+    }
+}
+
+impl Display for Changelog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in &self.changes {
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}",
+                c.time, c.kind, c.lang, c.platform, c.page
+            )?;
+        }
+
+        Ok(())
+    }
+}
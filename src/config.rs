@@ -2,6 +2,7 @@ use std::borrow::Cow;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::slice;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
@@ -174,13 +175,68 @@ impl Default for StyleConfig {
     }
 }
 
+/// One or more mirrors.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MirrorList {
+    One(Cow<'static, str>),
+    Many(Vec<String>),
+}
+
+impl MirrorList {
+    /// Get the mirrors in the order in which they should be tried.
+    pub fn urls(&self) -> Vec<&str> {
+        match self {
+            Self::One(url) => vec![url],
+            Self::Many(urls) => urls.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// One or more directories.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathList {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+impl PathList {
+    /// Get the directories in priority order.
+    pub fn paths(&self) -> &[PathBuf] {
+        match self {
+            Self::One(path) => slice::from_ref(path),
+            Self::Many(paths) => paths,
+        }
+    }
+}
+
+/// How pages are stored in the cache.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageMode {
+    /// Extract archives into `pages.<lang>` directories.
+    #[default]
+    Dir,
+    /// Keep the downloaded `tldr-pages.<lang>.zip` archives and read pages from them.
+    Zip,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct CacheConfig {
     /// Cache directory.
     pub dir: PathBuf,
-    /// The mirror of tldr-pages to use.
-    pub mirror: Cow<'static, str>,
+    /// The mirrors of tldr-pages to use, tried in order.
+    pub mirror: MirrorList,
+    /// Timeout for connecting to a mirror in seconds.
+    connect_timeout: u64,
+    /// Timeout for individual reads from a mirror in seconds.
+    read_timeout: u64,
+    /// The number of times a failed request is retried before moving on to the next mirror.
+    pub retries: u32,
+    /// Delay before the first retry in seconds. It is doubled on every subsequent retry.
+    retry_delay: u64,
     /// Automatically update the cache
     /// if it is older than `max_age` hours.
     pub auto_update: bool,
@@ -188,23 +244,98 @@ pub struct CacheConfig {
     max_age: u64,
     /// Languages to download.
     pub languages: Vec<String>,
+    /// Whether `languages` were taken from environment variables.
+    #[serde(skip)]
+    pub languages_from_env: bool,
+    /// Platforms to search for pages before `common` and all other platforms.
+    pub platform_order: Vec<String>,
+    /// Directories with custom pages, searched before the cache.
+    pub custom_pages_dir: PathList,
+    /// How to store downloaded pages.
+    pub storage: StorageMode,
+    /// The number of archives to download and extract at the same time.
+    pub download_threads: usize,
+    /// Maximum size of all files extracted from an archive in MiB.
+    max_extracted_size: u64,
+    /// Maximum size of a single file extracted from an archive in KiB.
+    max_page_size: u64,
+    /// Maximum number of entries in an archive.
+    pub max_archive_entries: usize,
+    /// Hex-encoded Ed25519 public keys trusted to sign the checksum file.
+    /// Signatures are not checked if this is empty.
+    public_keys: Vec<String>,
 }
 
 impl Default for CacheConfig {
     fn default() -> Self {
         Self {
             dir: Cache::locate(),
-            mirror: Cow::Borrowed(
+            mirror: MirrorList::One(Cow::Borrowed(
                 "https://raw.githubusercontent.com/tldr-pages/tldr-pages.github.io/main/assets",
-            ),
+            )),
+            connect_timeout: 10,
+            read_timeout: 30,
+            retries: 2,
+            retry_delay: 1,
             auto_update: true,
             // 2 weeks
             max_age: 24 * 7 * 2,
             languages: vec![],
+            languages_from_env: false,
+            platform_order: vec![],
+            custom_pages_dir: PathList::Many(vec![]),
+            storage: StorageMode::default(),
+            download_threads: 4,
+            max_extracted_size: 512,
+            max_page_size: 1024,
+            max_archive_entries: 250_000,
+            public_keys: vec![],
         }
     }
 }
 
+impl CacheConfig {
+    pub const fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout)
+    }
+
+    pub const fn read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout)
+    }
+
+    pub const fn retry_delay(&self) -> Duration {
+        Duration::from_secs(self.retry_delay)
+    }
+
+    /// Get the maximum size of all files extracted from an archive in bytes.
+    pub const fn max_extracted_size(&self) -> u64 {
+        self.max_extracted_size.saturating_mul(1024 * 1024)
+    }
+
+    /// Get the maximum size of a single file extracted from an archive in bytes.
+    pub const fn max_page_size(&self) -> u64 {
+        self.max_page_size.saturating_mul(1024)
+    }
+
+    /// Decode the trusted public keys.
+    pub fn public_keys(&self) -> Result<Vec<Vec<u8>>> {
+        self.public_keys
+            .iter()
+            .map(|key| {
+                util::hex_decode(key)
+                    .filter(|k| k.len() == 32)
+                    .ok_or_else(|| {
+                        Error::new(format!(
+                            "invalid public key in the config: '{key}'. \
+                        Ed25519 public keys are 64 hexadecimal characters long."
+                        ))
+                        .kind(ErrorKind::ParseToml)
+                    })
+            })
+            .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct OutputConfig {
@@ -272,30 +403,30 @@ impl Config {
     }
 
     pub fn new(cli_config_path: Option<PathBuf>) -> Result<Self> {
-        let cfg_res = if let Some(path) = cli_config_path {
+        let mut cfg = if let Some(path) = cli_config_path {
             if path.is_file() {
-                return Self::parse(&path);
+                Self::parse(&path)?
+            } else {
+                warnln!("'{}': not a file, ignoring --config", path.display());
+                Self::default()
             }
-
-            warnln!("'{}': not a file, ignoring --config", path.display());
-            Ok(Self::default())
         } else {
             let path = Self::locate();
             if path.is_file() {
-                return Self::parse(&path);
+                Self::parse(&path)?
+            } else {
+                Self::default()
             }
-
-            Ok(Self::default())
         };
 
-        cfg_res.map(|mut cfg| {
-            if cfg.cache.languages.is_empty() {
-                util::get_languages_from_env(&mut cfg.cache.languages);
-            }
-            // English pages should always be downloaded and searched.
-            cfg.cache.languages.push("en".to_string());
-            cfg
-        })
+        if cfg.cache.languages.is_empty() {
+            util::get_languages_from_env(&mut cfg.cache.languages);
+            cfg.cache.languages_from_env = !cfg.cache.languages.is_empty();
+        }
+        // English pages should always be downloaded and searched.
+        cfg.cache.languages.push("en".to_string());
+
+        Ok(cfg)
     }
 
     /// Get the default path to the config file.
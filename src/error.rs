@@ -1,8 +1,8 @@
-use std::fmt::{self, Display};
+use std::fmt::{self, Display, Write as _};
 use std::io::{self, Write};
-use std::path::Path;
 use std::process::ExitCode;
 use std::result::Result as StdResult;
+use std::sync::atomic::Ordering::Relaxed;
 
 use yansi::Color::Red;
 use yansi::Paint;
@@ -12,12 +12,29 @@ pub enum ErrorKind {
     ParsePage,
     Download,
     Io,
+    PageNotFound,
     Other,
 }
 
+impl ErrorKind {
+    /// Get the name of the kind used in JSON errors.
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ParseToml => "parse_toml",
+            Self::ParsePage => "parse_page",
+            Self::Download => "download",
+            Self::Io => "io",
+            Self::PageNotFound => "page_not_found",
+            Self::Other => "other",
+        }
+    }
+}
+
 pub struct Error {
     pub kind: ErrorKind,
     message: String,
+    /// Names of similar pages, if a page was not found.
+    suggestions: Vec<String>,
 }
 
 pub type Result<T> = StdResult<T, Error>;
@@ -44,6 +61,7 @@ impl Error {
         Self {
             kind: ErrorKind::Other,
             message: message.to_string(),
+            suggestions: vec![],
         }
     }
 
@@ -62,10 +80,10 @@ impl Error {
         self
     }
 
-    pub fn parse_page(page_path: &Path, i: usize, line: &str) -> Self {
+    pub fn parse_page(page: &str, i: usize, line: &str) -> Self {
         Error::new(format!(
             "'{}' is not a valid tldr page. (line {}):\n\n    {}",
-            page_path.display(),
+            page,
             i,
             Paint::new(line).bold(),
         ))
@@ -76,6 +94,66 @@ impl Error {
         Error::new("could not parse the checksum file").kind(ErrorKind::Download)
     }
 
+    pub fn sum_mismatch(expected: &str, actual: &str) -> Self {
+        Error::new(format!(
+            "SHA256 sum mismatch!\n\
+            expected : {expected}\n\
+            got      : {actual}"
+        ))
+        .kind(ErrorKind::Download)
+    }
+
+    pub fn bad_archive_entry(name: &str, reason: &str) -> Self {
+        Error::new(format!("rejected archive entry '{name}': {reason}")).kind(ErrorKind::Download)
+    }
+
+    /// Create an error for an archive that exceeds the limit set by `option` in the config.
+    pub fn archive_limit(message: &str, option: &str) -> Self {
+        Error::new(format!(
+            "{message}. If this is expected, increase '{option}' in the config file."
+        ))
+        .kind(ErrorKind::Download)
+    }
+
+    /// Create an error for a page in an archive that is larger than `max_page_size` bytes.
+    pub fn page_too_large(name: &str, max_page_size: u64) -> Self {
+        Error::archive_limit(
+            &format!(
+                "'{name}' is larger than {} KiB when extracted",
+                max_page_size / 1024
+            ),
+            "max_page_size",
+        )
+    }
+
+    pub fn bad_signature() -> Self {
+        Error::new(
+            "the signature of the checksum file is invalid or was not made by any of the \
+            trusted keys. The mirror might be compromised.",
+        )
+        .kind(ErrorKind::Download)
+    }
+
+    /// Create an error for a page that was not found, with suggestions of similar pages.
+    pub fn page_not_found(suggestions: &[String]) -> Self {
+        let mut e = if suggestions.is_empty() {
+            Error::new("page not found.")
+        } else {
+            let names: Vec<String> = suggestions
+                .iter()
+                .map(|name| Paint::new(name).bold().to_string())
+                .collect();
+
+            Error::new(format!(
+                "page not found. Did you mean: {}?",
+                names.join(", ")
+            ))
+        };
+
+        e.suggestions = suggestions.to_vec();
+        e.kind(ErrorKind::PageNotFound)
+    }
+
     pub fn desc_page_does_not_exist() -> String {
         format!(
             "Try running 'tldr --update'.\n\n\
@@ -88,21 +166,49 @@ impl Error {
         )
     }
 
+    /// Create an error that reports what went wrong with each mirror.
+    pub fn all_mirrors_failed(errors: Vec<(String, Error)>) -> Self {
+        let mut message = String::from("could not update the cache using any of the mirrors.");
+
+        for (mirror, e) in errors {
+            let _ = write!(message, "\n\n{}\n{e}", Paint::new(mirror).bold());
+        }
+
+        Error::new(message).kind(ErrorKind::Download)
+    }
+
     pub fn offline_no_cache() -> Self {
         Error::new("cache does not exist. Run tldr without --offline to download pages.")
             .kind(ErrorKind::Download)
     }
 
+    /// Format the error as a single-line JSON object:
+    /// `{"kind":"...","message":"...","suggestions":[...]}`. Colors are removed from the message.
+    fn to_json(&self) -> String {
+        let suggestions: Vec<String> = self.suggestions.iter().map(|s| json_string(s)).collect();
+
+        format!(
+            "{{\"kind\":{},\"message\":{},\"suggestions\":[{}]}}",
+            json_string(self.kind.name()),
+            json_string(&strip_ansi(&self.message)),
+            suggestions.join(",")
+        )
+    }
+
     /// Print the error message to stderr and return an appropriate `ExitCode`.
     pub fn exit_code(self) -> ExitCode {
-        let _ = writeln!(
-            io::stderr(),
-            "{} {self}",
-            Paint::new("error:").fg(Red).bold()
-        );
+        let _ = if crate::JSON_ERRORS.load(Relaxed) {
+            writeln!(io::stderr(), "{}", self.to_json())
+        } else {
+            writeln!(
+                io::stderr(),
+                "{} {self}",
+                Paint::new("error:").fg(Red).bold()
+            )
+        };
 
         match self.kind {
-            ErrorKind::Other | ErrorKind::Io => 1,
+            ErrorKind::Other | ErrorKind::Io | ErrorKind::PageNotFound => 1,
             ErrorKind::ParseToml => 3,
             ErrorKind::Download => 4,
             ErrorKind::ParsePage => 5,
@@ -111,6 +217,44 @@ impl Error {
     }
 }
 
+/// Remove ANSI escape sequences (colors) from `s`.
+fn strip_ansi(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip everything up to and including the final byte of the sequence.
+            chars.find(char::is_ascii_alphabetic);
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// Quote and escape `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut result = String::from('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => result += "\\\"",
+            '\\' => result += "\\\\",
+            '\n' => result += "\\n",
+            '\t' => result += "\\t",
+            c if c.is_control() => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
 macro_rules! from_impl {
     ( $from:ty, $kind:tt ) => {
         impl From<$from> for Error {
@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::store::PageStore;
+
+/// The first word of the index file, followed by the format version and the stamp of
+/// the installed pages.
+const HEADER: &str = "tlrc-index 3";
+
+/// A prebuilt list of languages, platforms and pages.
+///
+/// The file starts with a header line, followed by one line for every language and platform:
+/// `<lang>\t<platform>\t<start>\t<end>`, and an empty line. The rest of the file consists of
+/// sections with the pages of every platform at these byte offsets (relative to the end of the
+/// header), one line for every page: `<page>\t<description>`.
+///
+/// Only the header is read when the index is loaded. Pages are read when they are listed.
+pub struct Index {
+    file: RefCell<File>,
+    /// Offset of the first section in the file.
+    body_start: u64,
+    /// Byte ranges of the sections of every language and platform.
+    sections: BTreeMap<String, BTreeMap<String, Range<u64>>>,
+}
+
+impl Index {
+    /// Load the header of the index from `path`.
+    ///
+    /// Returns `None` if the index is missing, cannot be parsed, or is stale, i.e. it was
+    /// built when the installed pages had a different `stamp`.
+    pub fn load(path: &Path, stamp: &str) -> Option<Self> {
+        let mut reader = BufReader::new(File::open(path).ok()?);
+        let mut line = String::new();
+
+        reader.read_line(&mut line).ok()?;
+        if line.strip_suffix('\n')? != format!("{HEADER} {stamp}") {
+            return None;
+        }
+
+        let mut body_start = line.len() as u64;
+        let mut sections: BTreeMap<String, BTreeMap<_, _>> = BTreeMap::new();
+
+        loop {
+            line.clear();
+            reader.read_line(&mut line).ok()?;
+            body_start += line.len() as u64;
+
+            // The header ends with an empty line, a truncated file has no end.
+            let l = line.strip_suffix('\n')?;
+            if l.is_empty() {
+                break;
+            }
+
+            let mut spl = l.split('\t');
+            let (lang, platform) = (spl.next()?, spl.next()?);
+            let range = spl.next()?.parse().ok()?..spl.next()?.parse().ok()?;
+
+            sections
+                .entry(lang.to_string())
+                .or_default()
+                .insert(platform.to_string(), range);
+        }
+
+        Some(Self {
+            file: RefCell::new(reader.into_inner()),
+            body_start,
+            sections,
+        })
+    }
+
+    /// Get the languages in the index.
+    pub fn languages(&self) -> Vec<String> {
+        self.sections.keys().cloned().collect()
+    }
+
+    /// Get the platforms of `lang`.
+    pub fn platforms(&self, lang: &str) -> Vec<OsString> {
+        self.sections
+            .get(lang)
+            .map(|platforms| platforms.keys().map(OsString::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// List the file names of pages of `lang` for `platform`.
+    pub fn list(&self, lang: &str, platform: &str) -> Result<Vec<OsString>> {
+        let Some(range) = self.sections.get(lang).and_then(|p| p.get(platform)) else {
+            return Ok(vec![]);
+        };
+
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(self.body_start + range.start))?;
+        let mut section = String::new();
+        (&mut *file)
+            .take(range.end - range.start)
+            .read_to_string(&mut section)?;
+
+        Ok(section
+            .lines()
+            .filter_map(|l| l.split('\t').next())
+            .map(|name| format!("{name}.md").into())
+            .collect())
+    }
+}
+
+/// Pages of a platform, mapped to their one-line descriptions.
+type Pages = BTreeMap<String, String>;
+
+/// Collects pages from page stores and writes the index file.
+pub struct IndexBuilder {
+    /// The stamp of the installed pages (see `Cache::index_stamp`).
+    stamp: String,
+    languages: BTreeMap<String, BTreeMap<String, Pages>>,
+}
+
+impl IndexBuilder {
+    /// Create an empty index for the installed pages with `stamp`.
+    pub fn new(stamp: String) -> Self {
+        Self {
+            stamp,
+            languages: BTreeMap::new(),
+        }
+    }
+
+    /// Add all pages in `store` to the index as `lang`.
+    pub fn add(&mut self, lang: &str, store: &dyn PageStore) -> Result<()> {
+        let platforms = self.languages.entry(lang.to_string()).or_default();
+
+        for platform in store.platforms()? {
+            let pages = platforms
+                .entry(platform.to_string_lossy().into_owned())
+                .or_default();
+
+            for fname in store.list(&platform)? {
+                let fname = fname.to_string_lossy();
+                let Some(name) = fname.strip_suffix(".md") else {
+                    continue;
+                };
+
+                let desc = Self::read_description(store.open(&platform, &fname)?)?;
+                pages.insert(name.to_string(), desc);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the first line of the description of a page.
+    fn read_description(page: Box<dyn Read>) -> Result<String> {
+        for l in BufReader::new(page).lines() {
+            if let Some(desc) = l?.strip_prefix("> ") {
+                // Tabs are used as separators in the index file.
+                return Ok(desc.trim().replace('\t', " "));
+            }
+        }
+
+        Ok(String::new())
+    }
+
+    /// Write the index to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut header = format!("{HEADER} {}\n", self.stamp);
+        let mut body = String::new();
+
+        for (lang, platforms) in &self.languages {
+            for (platform, pages) in platforms {
+                let start = body.len();
+                for (page, desc) in pages {
+                    let _ = writeln!(body, "{page}\t{desc}");
+                }
+
+                let _ = writeln!(header, "{lang}\t{platform}\t{start}\t{}", body.len());
+            }
+        }
+
+        header.push('\n');
+        Ok(fs::write(path, header + &body)?)
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use fs4::FileExt;
+
+use crate::error::{Error, Result};
+use crate::util::infoln;
+
+const LOCK_FILE: &str = ".lock";
+/// How often to check whether the lock has been released.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An advisory lock that prevents multiple processes from modifying the cache at once.
+///
+/// This is an OS lock (`flock` or `LockFileEx`) on a file in the cache directory, so it is
+/// released by the OS when the process exits, even if it was killed. The lock file itself
+/// is never removed, because another process could have already opened it.
+///
+/// The lock is released when this is dropped.
+pub struct CacheLock {
+    _file: File,
+}
+
+/// Return `true` if `e` means that the file is locked by another process.
+fn is_contended(e: &io::Error) -> bool {
+    let contended = fs4::lock_contended_error();
+    e.kind() == contended.kind() && e.raw_os_error() == contended.raw_os_error()
+}
+
+impl CacheLock {
+    /// Get the path to the lock file in `dir`.
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(LOCK_FILE)
+    }
+
+    /// Return `true` if another process holds the lock for `dir`.
+    ///
+    /// This never fails. If the lock cannot be checked (e.g. the cache is read-only),
+    /// it is assumed not to be held.
+    pub fn is_held(dir: &Path) -> bool {
+        let Ok(file) = File::open(Self::path(dir)) else {
+            return false;
+        };
+
+        // Shared locks conflict only with the exclusive lock of a writer.
+        FileExt::try_lock_shared(&file).is_err_and(|e| is_contended(&e))
+    }
+
+    /// Try to acquire the lock without waiting. Returns `None` if another process holds it.
+    pub fn try_acquire(dir: &Path) -> Result<Option<Self>> {
+        fs::create_dir_all(dir)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(Self::path(dir))?;
+
+        match FileExt::try_lock_exclusive(&file) {
+            Ok(()) => {}
+            Err(e) if is_contended(&e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        // The PID is only informational, it is shown when waiting for the lock times out.
+        file.set_len(0)?;
+        writeln!(file, "{}", process::id())?;
+
+        Ok(Some(Self { _file: file }))
+    }
+
+    /// Acquire the lock, waiting at most `timeout` for other processes to release it.
+    pub fn acquire(dir: &Path, timeout: Duration) -> Result<Self> {
+        let start = Instant::now();
+        let mut waiting = false;
+
+        loop {
+            if let Some(lock) = Self::try_acquire(dir)? {
+                return Ok(lock);
+            }
+
+            if start.elapsed() > timeout {
+                let pid = fs::read_to_string(Self::path(dir)).unwrap_or_default();
+
+                return Err(Error::new(format!(
+                    "the cache is locked by another process (PID {}).",
+                    pid.trim(),
+                )));
+            }
+
+            if !waiting {
+                infoln!("waiting for another process to finish modifying the cache...");
+                waiting = true;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
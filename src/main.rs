@@ -1,8 +1,14 @@
 mod args;
 mod cache;
+mod changelog;
 mod config;
 mod error;
+mod index;
+mod lock;
+mod mirror;
 mod output;
+mod search;
+mod store;
 mod util;
 
 use std::process::ExitCode;
@@ -17,10 +23,12 @@ use crate::cache::Cache;
 use crate::config::Config;
 use crate::error::{Error, Result};
 use crate::output::PageRenderer;
-use crate::util::{infoln, init_color, warnln};
+use crate::util::{infoln, init_color, warnln, Dedup, DetectedPlatform};
 
 /// If this is set to true, do not print anything except pages and errors.
 static QUIET: AtomicBool = AtomicBool::new(false);
+/// If this is set to true, print errors as JSON objects.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
 
 fn main() -> ExitCode {
     match run() {
@@ -29,6 +37,63 @@ fn main() -> ExitCode {
     }
 }
 
+/// Download pages if the cache is empty, or update it if it is stale.
+fn update_if_needed(cache: &mut Cache, cfg: &Config, offline: bool) -> Result<()> {
+    if !cache.lang_installed(cache::ENGLISH_DIR) {
+        if offline {
+            return Err(Error::offline_no_cache());
+        }
+        infoln!("cache is empty, downloading...");
+        cache.update(&cfg.cache)?;
+    } else if cache.is_locked() {
+        // Pages are swapped in atomically, so the existing ones can still be shown.
+        warnln!("the cache is being updated by another process, showing existing pages.");
+    } else if cfg.cache.auto_update && cache.age()? > cfg.cache_max_age() {
+        let age = util::duration_fmt(cache.age()?.as_secs());
+        let age = Paint::new(age).fg(Green).bold();
+
+        if offline {
+            warnln!(
+                "cache is stale (last update: {age} ago). Run tldr without --offline to update."
+            );
+        } else {
+            infoln!("cache is stale (last update: {age} ago), updating...");
+            cache
+                .update(&cfg.cache)
+                .map_err(|e| e.describe(Error::DESC_AUTO_UPDATE_ERR))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the platforms to search first, from the command line, the config or the detected ones.
+fn platform_order(
+    cli_platforms: Vec<String>,
+    cfg: &Config,
+    detected: &DetectedPlatform,
+) -> Vec<String> {
+    let mut platforms = if !cli_platforms.is_empty() {
+        cli_platforms
+    } else if !cfg.cache.platform_order.is_empty() {
+        cfg.cache.platform_order.clone()
+    } else {
+        detected.platforms()
+    };
+
+    // "macos" should be an alias of "osx".
+    // Since the `macos` directory doesn't exist, this has to be changed before it
+    // gets passed to cache functions (which expect directory names).
+    for platform in &mut platforms {
+        if platform == "macos" {
+            *platform = "osx".to_string();
+        }
+    }
+    platforms.dedup_nosort();
+
+    platforms
+}
+
 fn run() -> Result<()> {
     let cli = Cli::parse();
 
@@ -44,6 +109,10 @@ fn run() -> Result<()> {
         QUIET.store(true, Relaxed);
     }
 
+    if cli.json_errors {
+        JSON_ERRORS.store(true, Relaxed);
+    }
+
     init_color(cli.color);
 
     let mut cfg = Config::new(cli.config)?;
@@ -58,7 +127,10 @@ fn run() -> Result<()> {
     // We need to clone() because this vector will not be sorted,
     // unlike the one in the config.
     let languages = cli.languages.unwrap_or_else(|| cfg.cache.languages.clone());
-    let cache = Cache::new(&cfg.cache.dir);
+    let mut cache = Cache::new(&cfg.cache.dir)
+        .custom_pages(cfg.cache.custom_pages_dir.paths())
+        .max_page_size(cfg.cache.max_page_size());
+    cache.remove_stale_staging()?;
 
     if cli.clean_cache {
         return cache.clean();
@@ -66,48 +138,48 @@ fn run() -> Result<()> {
 
     if cli.update {
         // update() should never use languages from --language.
-        return cache.update(&cfg.cache.mirror, &mut cfg.cache.languages);
+        return cache.update(&cfg.cache);
     }
 
-    if !cache.subdir_exists(cache::ENGLISH_DIR) {
-        if cli.offline {
-            return Err(Error::offline_no_cache());
-        }
-        infoln!("cache is empty, downloading...");
-        cache.update(&cfg.cache.mirror, &mut cfg.cache.languages)?;
-    } else if cfg.cache.auto_update && cache.age()? > cfg.cache_max_age() {
-        let age = util::duration_fmt(cache.age()?.as_secs());
-        let age = Paint::new(age).fg(Green).bold();
+    if let Some(path) = cli.import {
+        return cache.import(
+            &cfg.cache,
+            &path,
+            languages_are_from_cli.then_some(&languages),
+        );
+    }
 
-        if cli.offline {
-            warnln!(
-                "cache is stale (last update: {age} ago). Run tldr without --offline to update."
-            );
-        } else {
-            infoln!("cache is stale (last update: {age} ago), updating...");
-            cache
-                .update(&cfg.cache.mirror, &mut cfg.cache.languages)
-                .map_err(|e| e.describe(Error::DESC_AUTO_UPDATE_ERR))?;
-        }
+    if let Some(path) = cli.export {
+        return cache.export(&path);
     }
 
-    // "macos" should be an alias of "osx".
-    // Since the `macos` directory doesn't exist, this has to be changed before it
-    // gets passed to cache functions (which expect directory names).
-    let platform = if cli.platform == "macos" {
-        "osx"
-    } else {
-        &cli.platform
-    };
+    if cli.verify_cache {
+        return cache.verify(&cfg.cache, cli.repair);
+    }
+
+    if cli.whats_new {
+        return cache.whats_new(cli.since);
+    }
+
+    update_if_needed(&mut cache, &cfg, cli.offline)?;
+
+    let detected = DetectedPlatform::detect();
+    let platforms = platform_order(cli.platforms, &cfg, &detected);
 
     if cli.list {
-        return cache.list_for(platform);
+        return cache.list_for(&platforms);
     }
     if cli.list_all {
         return cache.list_all();
     }
     if cli.info {
-        return cache.info(&cfg);
+        return cache.info(&cfg, &detected, &languages, languages_are_from_cli);
+    }
+    if let Some(terms) = cli.search {
+        return cache.search(&terms);
+    }
+    if let Some(page) = cli.list_translations {
+        return cache.list_translations(&page.join("-").to_lowercase());
     }
     if cli.list_platforms {
         return cache.list_platforms();
@@ -117,16 +189,20 @@ fn run() -> Result<()> {
     }
 
     let page_name = cli.page.join("-").to_lowercase();
-    let page_paths = cache.find(&page_name, &languages, platform)?;
+    let page_paths = if cli.no_follow_aliases {
+        cache.find(&page_name, &languages, &platforms)?
+    } else {
+        cache.find_following_aliases(&page_name, &languages, &platforms)?
+    };
 
     if page_paths.is_empty() {
-        let mut e = Error::new("page not found.");
+        let mut e = Error::page_not_found(&cache.suggest(&page_name));
         return if languages_are_from_cli {
             e = e.describe("Try running tldr without --language.");
 
             if !languages
                 .iter()
-                .all(|x| cache.subdir_exists(&format!("pages.{x}")))
+                .all(|x| cache.lang_installed(&format!("pages.{x}")))
             {
                 e = e.describe(Error::DESC_LANG_NOT_INSTALLED);
             }
@@ -137,5 +213,5 @@ fn run() -> Result<()> {
         };
     }
 
-    PageRenderer::print_cache_result(&page_paths, &cfg)
+    PageRenderer::print_cache_result(&cache, &page_paths, &languages, &cfg)
 }
@@ -0,0 +1,193 @@
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use ureq::Agent;
+
+use crate::config::CacheConfig;
+use crate::error::{Error, ErrorKind, Result};
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), '/', env!("CARGO_PKG_VERSION"));
+
+/// A source of tldr pages archives and the checksum file.
+pub enum Mirror {
+    /// A mirror available over HTTP(S).
+    Http {
+        url: String,
+        agent: Agent,
+        retries: u32,
+        retry_delay: Duration,
+    },
+    /// A local directory with the same layout as the HTTP(S) mirror.
+    Local(PathBuf),
+}
+
+/// Validators used to make conditional requests.
+#[derive(Default, Clone)]
+pub struct Validators {
+    /// The `ETag` header.
+    pub etag: Option<String>,
+    /// The `Last-Modified` header.
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// A file fetched from a mirror.
+pub struct Fetched {
+    reader: Box<dyn Read + Send>,
+    /// Set if reading from the mirror failed, in which case the request can be retried.
+    read_failed: bool,
+    /// Validators of the file (HTTP only).
+    pub validators: Validators,
+}
+
+impl Fetched {
+    /// Copy the contents of the file to `writer`.
+    ///
+    /// Errors that occur while reading from the mirror are reported as download errors.
+    pub fn copy_to<W: Write>(&mut self, writer: &mut W) -> Result<()> {
+        let mut buf = [0; 8192];
+
+        loop {
+            let n = match self.reader.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.read_failed = true;
+                    return Err(Error::new(e).kind(ErrorKind::Download));
+                }
+            };
+
+            writer.write_all(&buf[..n])?;
+        }
+    }
+
+    /// Read the contents of the file into a `String`.
+    pub fn read_to_string(&mut self) -> Result<String> {
+        let mut buf = vec![];
+        self.copy_to(&mut buf)?;
+        String::from_utf8(buf).map_err(|e| Error::new(e).kind(ErrorKind::Download))
+    }
+}
+
+impl Display for Mirror {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http { url, .. } => url.fmt(f),
+            Self::Local(path) => path.display().fmt(f),
+        }
+    }
+}
+
+impl Mirror {
+    /// Create a mirror from a URL. `file://` URLs and anything that is not
+    /// an HTTP(S) URL are treated as paths to local directories.
+    pub fn new(url: &str, cfg: &CacheConfig) -> Self {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            let agent = ureq::builder()
+                .user_agent(USER_AGENT)
+                .try_proxy_from_env(true)
+                .timeout_connect(cfg.connect_timeout())
+                .timeout_read(cfg.read_timeout())
+                .build();
+
+            Self::Http {
+                url: url.trim_end_matches('/').to_string(),
+                agent,
+                retries: cfg.retries,
+                retry_delay: cfg.retry_delay(),
+            }
+        } else {
+            Self::Local(PathBuf::from(url.strip_prefix("file://").unwrap_or(url)))
+        }
+    }
+
+    /// Fetch a file from the mirror and pass it to `f`.
+    ///
+    /// For HTTP mirrors, a conditional request is made using `validators`, and `None` is
+    /// returned if the server responds with 304 Not Modified. Connection errors, server errors
+    /// and errors while reading the response are retried. `f` is called again for every
+    /// attempt, so it must discard anything it has written in a previous one.
+    pub fn fetch<T, F>(&self, fname: &str, validators: &Validators, mut f: F) -> Result<Option<T>>
+    where
+        F: FnMut(&mut Fetched) -> Result<T>,
+    {
+        match self {
+            Self::Http {
+                url,
+                agent,
+                retries,
+                retry_delay,
+            } => {
+                let mut delay = *retry_delay;
+                let mut attempt = 0;
+
+                loop {
+                    let mut request = agent.get(&format!("{url}/{fname}"));
+
+                    if let Some(etag) = &validators.etag {
+                        request = request.set("If-None-Match", etag);
+                    }
+                    if let Some(last_modified) = &validators.last_modified {
+                        request = request.set("If-Modified-Since", last_modified);
+                    }
+
+                    match request.call() {
+                        Ok(resp) if resp.status() == 304 => return Ok(None),
+                        Ok(resp) => {
+                            let mut fetched = Fetched {
+                                validators: Validators {
+                                    etag: resp.header("ETag").map(str::to_string),
+                                    last_modified: resp.header("Last-Modified").map(str::to_string),
+                                },
+                                read_failed: false,
+                                reader: Box::new(resp.into_reader()),
+                            };
+
+                            match f(&mut fetched) {
+                                Err(_) if attempt < *retries && fetched.read_failed => {}
+                                result => return result.map(Some),
+                            }
+                        }
+                        Err(e) if attempt < *retries && is_transient(&e) => {}
+                        Err(e) => return Err(e.into()),
+                    }
+
+                    attempt += 1;
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+            }
+            Self::Local(dir) => {
+                let path = dir.join(fname);
+                let file = File::open(&path).map_err(|e| {
+                    Error::new(format!("'{}': {e}", path.display())).kind(ErrorKind::Download)
+                })?;
+
+                f(&mut Fetched {
+                    reader: Box::new(file),
+                    read_failed: false,
+                    validators: Validators::default(),
+                })
+                .map(Some)
+            }
+        }
+    }
+}
+
+/// Return `true` if retrying the request might succeed.
+fn is_transient(e: &ureq::Error) -> bool {
+    match e {
+        ureq::Error::Status(code, _) => *code == 429 || *code >= 500,
+        ureq::Error::Transport(_) => true,
+    }
+}
@@ -1,15 +1,16 @@
 use std::borrow::Cow;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::Ordering::Relaxed;
 
 use yansi::Color::Green;
 use yansi::{Paint, Style};
 
+use crate::cache::{Cache, PageRef};
 use crate::config::Config;
 use crate::error::{Error, ErrorKind, Result};
-use crate::util::{warnln, PagePathExt};
+use crate::util::{infoln, warnln, PagePathExt};
 
 const TITLE: &str = "# ";
 const DESC: &str = "> ";
@@ -28,16 +29,22 @@ struct RenderStyles {
 }
 
 pub struct PageRenderer<'a> {
-    /// Path to the page.
-    path: &'a Path,
-    /// A BufReader containing the page.
-    reader: BufReader<File>,
+    /// Where the page is stored, shown in error messages.
+    source: String,
+    /// The platform of the page, shown in the title if enabled.
+    platform: Option<String>,
+    /// A `BufReader` containing the page.
+    reader: BufReader<Box<dyn Read>>,
     /// A buffered handle to standard output.
     stdout: BufWriter<io::StdoutLock<'static>>,
     /// The line of the page that is currently being worked with.
     current_line: String,
     /// The line number of the current line.
     lnum: usize,
+    /// Patches with local examples appended to the page.
+    patches: &'a [PathBuf],
+    /// Whether the current line comes from a patch.
+    local: bool,
     /// Style configuration.
     style: RenderStyles,
     /// Other options.
@@ -54,7 +61,6 @@ impl<'a> PageRenderer<'a> {
 
         let mut buf = String::new();
 
-
         for (i, part) in split.into_iter().enumerate() {
             // Only odd indexes contain the part to be highlighted.
             // "aa `bb` cc `dd` ee"
@@ -64,8 +70,6 @@ impl<'a> PageRenderer<'a> {
             // 3: "dd"      (highlighted)
             // 4: " ee"
 
-
-
             if i % 2 == 0 {
                 buf += &style_normal.paint(part).to_string();
             } else {
@@ -113,7 +117,6 @@ impl<'a> PageRenderer<'a> {
 
         // split by position of first whitespace
 
-
         // Highlight beginning not found.
         if split.len() == 1 {
             let no_arg: Vec<&str> = s.splitn(2, ' ').collect();
@@ -153,25 +156,67 @@ impl<'a> PageRenderer<'a> {
         (*buf.last().expect("not description found")).to_string()
     }
 
-
     /// Print or render the page according to the provided config.
     pub fn print(path: &'a Path, cfg: &'a Config) -> Result<()> {
-        let mut page = File::open(path)
+        let page = File::open(path)
             .map_err(|e| Error::new(format!("'{}': {e}", path.display())).kind(ErrorKind::Io))?;
 
+        let platform = path.page_platform().map(Cow::into_owned);
+        Self::print_from(
+            path.display().to_string(),
+            platform,
+            Box::new(page),
+            &[],
+            cfg,
+        )
+    }
+
+    /// Return `true` if `line` belongs to the title or description of a patch,
+    /// which are not shown.
+    fn is_patch_header(line: &str) -> bool {
+        line.starts_with(TITLE) || line.starts_with(DESC) || line.trim().is_empty()
+    }
+
+    /// Print or render the page read from `page`, followed by the examples from `patches`.
+    /// `source` is used in messages and `platform` in the title.
+    fn print_from(
+        source: String,
+        platform: Option<String>,
+        mut page: Box<dyn Read>,
+        patches: &'a [PathBuf],
+        cfg: &'a Config,
+    ) -> Result<()> {
         if cfg.output.raw_markdown {
-            io::copy(&mut page, &mut io::stdout()).map_err(|e| {
-                Error::new(format!("'{}': {e}", path.display())).kind(ErrorKind::Io)
-            })?;
+            let mut stdout = io::stdout().lock();
+            io::copy(&mut page, &mut stdout)
+                .map_err(|e| Error::new(format!("'{source}': {e}")).kind(ErrorKind::Io))?;
+
+            for patch in patches {
+                let patch_contents = fs::read_to_string(patch).map_err(|e| {
+                    Error::new(format!("'{}': {e}", patch.display())).kind(ErrorKind::Io)
+                })?;
+
+                writeln!(stdout)?;
+                for l in patch_contents
+                    .lines()
+                    .skip_while(|l| Self::is_patch_header(l))
+                {
+                    writeln!(stdout, "{l}")?;
+                }
+            }
+
             return Ok(());
         }
 
         Self {
-            path,
+            source,
+            platform,
             reader: BufReader::new(page),
             stdout: BufWriter::new(io::stdout().lock()),
             current_line: String::new(),
             lnum: 0,
+            patches,
+            local: false,
             style: RenderStyles {
                 title: cfg.style.title.into(),
                 desc: cfg.style.description.into(),
@@ -184,26 +229,30 @@ impl<'a> PageRenderer<'a> {
             },
             cfg,
         }
-            .render()
+        .render()
     }
 
     /// Print the first page that was found and warnings for every other page.
-    pub fn print_cache_result(paths: &'a [PathBuf], cfg: &'a Config) -> Result<()> {
-        if !crate::QUIET.load(Relaxed) && paths.len() != 1 {
+    ///
+    /// A note is shown if the page is not in the first of `languages`.
+    pub fn print_cache_result(
+        cache: &Cache,
+        pages: &'a [PageRef],
+        languages: &[String],
+        cfg: &'a Config,
+    ) -> Result<()> {
+        if !crate::QUIET.load(Relaxed) && pages.len() != 1 {
             let mut stderr = io::stderr().lock();
-            let other_pages = &paths[1..];
+            let other_pages = &pages[1..];
             let width = other_pages
                 .iter()
-                .map(|x| x.page_platform().unwrap().len())
+                .map(|x| x.platform.len())
                 .fold(0, |max, cur| if cur > max { cur } else { max });
 
             warnln!("{} page(s) found for other platforms:", other_pages.len());
 
-            for (i, path) in other_pages.iter().enumerate() {
-                // The path always ends with the page file, and its parent is always the
-                // platform directory. This is safe to unwrap.
-                let name = path.page_name().unwrap();
-                let platform = path.page_platform().unwrap();
+            for (i, page) in other_pages.iter().enumerate() {
+                let PageRef { name, platform, .. } = page;
 
                 writeln!(
                     stderr,
@@ -214,8 +263,26 @@ impl<'a> PageRenderer<'a> {
         }
 
         // This is safe to unwrap - errors would have already been catched in run().
-        let first = paths.first().unwrap();
-        Self::print(first, cfg)
+        let first = pages.first().unwrap();
+
+        // Languages that are not installed (e.g. `de_DE` when only `de` is) are skipped.
+        if let (Some(lang), Some(preferred)) = (&first.lang, cache.preferred_language(languages)?) {
+            if *lang != preferred {
+                let name = &first.name;
+                infoln!(
+                    "showing '{name}' in '{lang}' instead of '{preferred}'. \
+                    Run 'tldr --list-translations {name}' to see all translations."
+                );
+            }
+        }
+        let patches = cache.find_patches(first);
+        PageRenderer::print_from(
+            first.to_string(),
+            Some(first.platform.clone()),
+            cache.open_page(first)?,
+            &patches,
+            cfg,
+        )
     }
 
     /// Load the next line into the line buffer.
@@ -224,7 +291,7 @@ impl<'a> PageRenderer<'a> {
         self.lnum += 1;
         self.reader
             .read_line(&mut self.current_line)
-            .map_err(|e| Error::new(format!("'{}': {e}", self.path.display())))
+            .map_err(|e| Error::new(format!("'{}': {e}", self.source)))
     }
 
     /// Write the current line to the page buffer as a title.
@@ -238,7 +305,7 @@ impl<'a> PageRenderer<'a> {
 
         let line = self.current_line.strip_prefix(TITLE).unwrap();
         let title = if self.cfg.output.platform_title {
-            if let Some(platform) = self.path.page_platform() {
+            if let Some(platform) = &self.platform {
                 Cow::Owned(format!("{platform}/{line}"))
             } else {
                 Cow::Borrowed(line)
@@ -281,6 +348,21 @@ impl<'a> PageRenderer<'a> {
             self.current_line.strip_prefix(BULLET).unwrap()
         };
 
+        if self.local {
+            // Mark examples from patches, so that they can be told apart from upstream ones.
+            let bullet = self.hl_code(
+                &self.hl_url(line.trim_end(), self.style.bullet),
+                self.style.bullet,
+            );
+            writeln!(
+                self.stdout,
+                "{}{bullet} {}",
+                " ".repeat(self.cfg.indent.bullet),
+                self.style.bullet.dimmed().paint("(local)")
+            )?;
+            return Ok(());
+        }
+
         let bullet = self.hl_code(&self.hl_url(line, self.style.bullet), self.style.bullet);
         write!(
             self.stdout,
@@ -307,29 +389,29 @@ impl<'a> PageRenderer<'a> {
             .trim_end()
             .strip_suffix('`')
             .ok_or_else(|| {
-                Error::parse_page(self.path, self.lnum, &self.current_line)
+                Error::parse_page(&self.source, self.lnum, &self.current_line)
                     .describe("\nEvery line with an example must end with a backtick '`'.")
             })?;
 
-
         // strip command name if it exists
 
         // let example = example.strip_prefix(cmd_name).expect("command name not found");
         let cmd_line = line;
 
-
         let example = self
             .hl_placeholder(line, self.style.example)
             // Remove the extra spaces and backslashes.
             .replace(" \\{\\{ ", "{{")
             .replace(" \\}\\} ", "}}");
 
-
-        let cmd_name = *cmd_line.splitn(2, ' ').collect::<Vec<&str>>().first().expect("no command found");
+        let cmd_name = *cmd_line
+            .splitn(2, ' ')
+            .collect::<Vec<&str>>()
+            .first()
+            .expect("no command found");
 
         let cmd_name_painted = self.style.command_name.paint(cmd_name).to_string();
 
-
         writeln!(
             self.stdout,
             "{}{cmd_name_painted} {example}",
@@ -348,16 +430,42 @@ impl<'a> PageRenderer<'a> {
         Ok(())
     }
 
-    /// Write a command name to the page buffer as an example.
+    /// Render the page and its patches to standard output.
+    fn render(&mut self) -> Result<()> {
+        self.render_lines()?;
 
+        for patch in self.patches {
+            let file = File::open(patch).map_err(|e| {
+                Error::new(format!("'{}': {e}", patch.display())).kind(ErrorKind::Io)
+            })?;
 
+            self.source = patch.display().to_string();
+            self.reader = BufReader::new(Box::new(file));
+            self.lnum = 0;
+            self.local = true;
 
+            // Separate the local examples from the page.
+            self.add_newline()?;
+            self.render_lines()?;
+        }
 
+        self.add_newline()?;
+        Ok(self.stdout.flush()?)
+    }
 
+    /// Render all lines from the reader.
+    fn render_lines(&mut self) -> Result<()> {
+        // Only examples from patches are shown.
+        let mut in_patch_header = self.local;
 
-    /// Render the page to standard output.
-    fn render(&mut self) -> Result<()> {
         while self.next_line()? != 0 {
+            if in_patch_header {
+                if Self::is_patch_header(&self.current_line) {
+                    continue;
+                }
+                in_patch_header = false;
+            }
+
             if self.current_line.starts_with(TITLE) {
                 self.add_title()?;
             } else if self.current_line.starts_with(DESC) {
@@ -370,14 +478,13 @@ impl<'a> PageRenderer<'a> {
                 self.add_newline()?;
             } else {
                 return Err(
-                    Error::parse_page(self.path, self.lnum, &self.current_line).describe(
+                    Error::parse_page(&self.source, self.lnum, &self.current_line).describe(
                         "\nEvery non-empty line must begin with either '# ', '> ', '- ' or '`'.",
                     ),
                 );
             }
         }
 
-        self.add_newline()?;
-        Ok(self.stdout.flush()?)
+        Ok(())
     }
 }
@@ -0,0 +1,124 @@
+use std::iter;
+use std::ops::Range;
+
+use yansi::Color::Yellow;
+use yansi::Paint;
+
+/// The part of a page a line belongs to, ordered from the least to the most relevant.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LineKind {
+    Example,
+    Description,
+    Title,
+}
+
+/// The best matching line of a page.
+pub struct Hit {
+    pub kind: LineKind,
+    /// The number of distinct terms found in the line.
+    pub n_terms: usize,
+    /// The line without markdown syntax.
+    pub line: String,
+}
+
+impl Hit {
+    /// Search the page `contents` for `terms` (which must be lowercase).
+    ///
+    /// Returns `None` unless every term occurs somewhere in the page.
+    pub fn find(contents: &str, terms: &[String]) -> Option<Self> {
+        let page = contents.to_lowercase();
+        if !terms.iter().all(|term| page.contains(term.as_str())) {
+            return None;
+        }
+
+        let mut best: Option<Self> = None;
+
+        for l in contents.lines() {
+            let (kind, line) = if let Some(line) = l.strip_prefix("# ") {
+                (LineKind::Title, line)
+            } else if let Some(line) = l.strip_prefix("> ") {
+                (LineKind::Description, line)
+            } else if let Some(line) = l.strip_prefix("- ") {
+                (LineKind::Example, line)
+            } else if let Some(line) = l.strip_prefix('`') {
+                (LineKind::Example, line.trim_end().trim_end_matches('`'))
+            } else {
+                continue;
+            };
+
+            let lowercase = line.to_lowercase();
+            let n_terms = terms
+                .iter()
+                .filter(|term| lowercase.contains(term.as_str()))
+                .count();
+
+            if n_terms == 0 {
+                continue;
+            }
+
+            // A title match beats a description match, which beats an example match.
+            if best
+                .as_ref()
+                .map_or(true, |b| (kind, n_terms) > (b.kind, b.n_terms))
+            {
+                best = Some(Self {
+                    kind,
+                    n_terms,
+                    line: line.to_string(),
+                });
+            }
+        }
+
+        best
+    }
+
+    /// Get the line with all occurrences of `terms` highlighted.
+    pub fn highlighted(&self, terms: &[String]) -> String {
+        let (lowercase, spans) = lowercase_with_spans(&self.line);
+        let mut highlight = vec![false; self.line.len()];
+
+        for term in terms {
+            for (i, _) in lowercase.match_indices(term.as_str()) {
+                let span = spans[i].start..spans[i + term.len() - 1].end;
+                highlight[span].fill(true);
+            }
+        }
+
+        let mut result = String::new();
+        let mut start = 0;
+
+        while start < self.line.len() {
+            let is_hl = highlight[start];
+            let end = (start..self.line.len())
+                .find(|&i| highlight[i] != is_hl && self.line.is_char_boundary(i))
+                .unwrap_or(self.line.len());
+            let part = &self.line[start..end];
+
+            if is_hl {
+                result += &Paint::new(part).fg(Yellow).bold().to_string();
+            } else {
+                result += part;
+            }
+            start = end;
+        }
+
+        result
+    }
+}
+
+/// Lowercase `s` like `str::to_lowercase`, which is also used for the terms.
+///
+/// Lowercasing can change the length of some characters, so the byte range of the original
+/// character is returned for every byte of the lowercase string.
+fn lowercase_with_spans(s: &str) -> (String, Vec<Range<usize>>) {
+    let mut spans = Vec::with_capacity(s.len());
+
+    for (i, c) in s.char_indices() {
+        let len = c.to_lowercase().map(char::len_utf8).sum();
+        spans.extend(iter::repeat(i..i + c.len_utf8()).take(len));
+    }
+
+    // `str::to_lowercase` differs from lowercasing every character only by mapping a final
+    // sigma to 'ς', which has the same length as 'σ'.
+    (s.to_lowercase(), spans)
+}
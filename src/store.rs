@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use zip::ZipArchive;
+
+use crate::error::{Error, ErrorKind, Result};
+
+/// Storage of pages in a single language.
+pub trait PageStore {
+    /// List the platforms.
+    fn platforms(&self) -> Result<Vec<OsString>>;
+
+    /// List the file names of all pages for `platform`.
+    /// Returns an empty `Vec` if the platform does not exist.
+    fn list(&self, platform: &OsStr) -> Result<Vec<OsString>>;
+
+    /// Return `true` if the page `fname` exists for `platform`.
+    fn contains(&self, platform: &OsStr, fname: &str) -> bool;
+
+    /// Open the page `fname` for `platform`.
+    fn open(&self, platform: &OsStr, fname: &str) -> Result<Box<dyn Read>>;
+}
+
+/// Pages extracted into a directory (`pages.<lang>/<platform>/<page>.md`).
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+}
+
+impl PageStore for DirStore {
+    fn platforms(&self) -> Result<Vec<OsString>> {
+        let mut result = vec![];
+
+        for entry in fs::read_dir(&self.dir)? {
+            result.push(entry?.file_name());
+        }
+
+        Ok(result)
+    }
+
+    fn list(&self, platform: &OsStr) -> Result<Vec<OsString>> {
+        match fs::read_dir(self.dir.join(platform)) {
+            Ok(entries) => {
+                let entries = entries.map(|res| res.map(|ent| ent.file_name()));
+                Ok(entries.collect::<io::Result<Vec<OsString>>>()?)
+            }
+            // If the directory does not exist, return an empty Vec instead of an error
+            // (some platform directories do not exist in some translations).
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn contains(&self, platform: &OsStr, fname: &str) -> bool {
+        self.dir.join(platform).join(fname).is_file()
+    }
+
+    fn open(&self, platform: &OsStr, fname: &str) -> Result<Box<dyn Read>> {
+        Ok(Box::new(File::open(self.dir.join(platform).join(fname))?))
+    }
+}
+
+/// Pages kept in a language archive (`tldr-pages.<lang>.zip`).
+pub struct ZipStore {
+    archive: RefCell<ZipArchive<BufReader<File>>>,
+    /// File names of pages for every platform, read from the central directory.
+    pages: BTreeMap<OsString, BTreeSet<OsString>>,
+    /// Pages larger than this are not read.
+    max_page_size: u64,
+}
+
+impl ZipStore {
+    pub fn open(path: &Path, max_page_size: u64) -> Result<Self> {
+        let archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+        let mut pages: BTreeMap<OsString, BTreeSet<OsString>> = BTreeMap::new();
+
+        for name in archive.file_names() {
+            // Only `<platform>/<page>.md` entries are pages.
+            let Some((platform, fname)) = name.split_once('/') else {
+                continue;
+            };
+            if fname.is_empty() || fname.contains('/') {
+                continue;
+            }
+
+            pages
+                .entry(platform.into())
+                .or_default()
+                .insert(fname.into());
+        }
+
+        Ok(Self {
+            archive: RefCell::new(archive),
+            pages,
+            max_page_size,
+        })
+    }
+}
+
+impl PageStore for ZipStore {
+    fn platforms(&self) -> Result<Vec<OsString>> {
+        Ok(self.pages.keys().cloned().collect())
+    }
+
+    fn list(&self, platform: &OsStr) -> Result<Vec<OsString>> {
+        Ok(self
+            .pages
+            .get(platform)
+            .map(|pages| pages.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn contains(&self, platform: &OsStr, fname: &str) -> bool {
+        self.pages
+            .get(platform)
+            .is_some_and(|pages| pages.contains(OsStr::new(fname)))
+    }
+
+    fn open(&self, platform: &OsStr, fname: &str) -> Result<Box<dyn Read>> {
+        let mut archive = self.archive.borrow_mut();
+        let name = format!("{}/{fname}", platform.to_string_lossy());
+        let mut page = archive
+            .by_name(&name)
+            .map_err(|e| Error::new(e).kind(ErrorKind::Io))?;
+
+        // The entry borrows the archive, so it has to be read into memory.
+        // The size stored in the archive cannot be trusted, so the data is read
+        // until one byte past the limit.
+        let mut buf = vec![];
+        (&mut page)
+            .take(self.max_page_size.saturating_add(1))
+            .read_to_end(&mut buf)?;
+
+        if buf.len() as u64 > self.max_page_size {
+            return Err(Error::page_too_large(&name, self.max_page_size));
+        }
+
+        Ok(Box::new(Cursor::new(buf)))
+    }
+}
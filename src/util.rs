@@ -1,13 +1,14 @@
 use std::borrow::Cow;
 use std::env;
 use std::ffi::OsStr;
-use std::io::{self, IsTerminal};
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
 use std::iter;
 use std::mem;
 use std::path::Path;
 
 use clap::ColorChoice;
-use ring::digest::{digest, SHA256};
+use ring::digest::{Context, SHA256};
 use yansi::Paint;
 
 /// Prints a warning.
@@ -34,56 +35,189 @@ macro_rules! infoln {
     };
 }
 
-/// Prints a status message without a trailing newline.
-macro_rules! info_start {
-    ( $( $arg:tt )* ) => {
-        if !$crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
-            use std::io::Write;
-            let mut stderr = std::io::stderr().lock();
-            write!(stderr, "{} ", yansi::Paint::new("info:").fg(yansi::Color::Cyan).bold())?;
-            write!(stderr, $($arg)*)?;
+pub(crate) use {infoln, warnln};
+
+/// Normalize a POSIX locale name (`language[_territory][.codeset][@modifier]`)
+/// or a language tag (`zh-Hant-TW`) to a language and an optional territory.
+///
+/// Returns `None` for the `C` and `POSIX` locales and invalid names.
+fn parse_locale(locale: &str) -> Option<(String, Option<String>)> {
+    let locale = locale.split('@').next()?;
+    let locale = locale.split('.').next()?;
+    let mut parts = locale.split(['_', '-']);
+
+    let lang = parts.next()?;
+    // ISO 639-1 and ISO 639-2/3 codes (the latter for languages without a 2-letter code).
+    if !(2..=3).contains(&lang.len()) || !lang.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let lang = lang.to_ascii_lowercase();
+
+    let mut script = None;
+    let mut territory = None;
+
+    for part in parts {
+        if part.len() == 4 && part.chars().all(|c| c.is_ascii_alphabetic()) {
+            script = Some(part.to_ascii_lowercase());
+        } else if part.len() == 2 && part.chars().all(|c| c.is_ascii_alphabetic())
+            || part.len() == 3 && part.chars().all(|c| c.is_ascii_digit())
+        {
+            territory = Some(part.to_ascii_uppercase());
+        } else {
+            return None;
         }
-    };
-}
+    }
 
-/// End the status message started using `info_start`.
-macro_rules! info_end {
-    ( $( $arg:tt )* ) => {
-        if !$crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
-            use std::io::Write;
-            writeln!(std::io::stderr(), $($arg)*)?;
-        }
-    };
-}
+    // Traditional Chinese pages are in `zh_TW`.
+    if lang == "zh" && territory.is_none() && script.as_deref() == Some("hant") {
+        territory = Some("TW".to_string());
+    }
 
-pub(crate) use {info_end, info_start, infoln, warnln};
+    Some((lang, territory))
+}
 
-/// Get languages from environment variables according to the tldr client specification.
-pub fn get_languages_from_env(out_vec: &mut Vec<String>) {
+/// Get languages from environment variables according to the tldr client specification,
+/// along with the names of the variables they were taken from.
+pub fn languages_from_env() -> Vec<(String, &'static str)> {
     // https://github.com/tldr-pages/tldr/blob/main/CLIENT-SPECIFICATION.md#language
 
-    let Ok(var_lang) = env::var("LANG") else {
-        return;
+    // The locale of messages is taken from the first variable that is set (POSIX).
+    let Some((locale_var, locale)) =
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .find_map(|var| {
+                env::var(var)
+                    .ok()
+                    .filter(|x| !x.is_empty())
+                    .map(|x| (var, x))
+            })
+    else {
+        return vec![];
     };
 
-    let var_language = env::var("LANGUAGE");
+    // LANGUAGE is ignored if the locale is C or POSIX (like in GNU gettext).
+    if parse_locale(&locale).is_none() {
+        return vec![];
+    }
 
+    let var_language = env::var("LANGUAGE").unwrap_or_default();
     let languages = var_language
-        .as_deref()
-        .unwrap_or("")
         .split(':')
-        .chain(iter::once(&*var_lang));
+        .map(|lang| (lang, "LANGUAGE"))
+        .chain(iter::once((&*locale, locale_var)));
+
+    let mut result = vec![];
+
+    for (lang, var) in languages {
+        let Some((lang, territory)) = parse_locale(lang) else {
+            continue;
+        };
+
+        if let Some(territory) = territory {
+            // <language>_<territory>
+            result.push((format!("{lang}_{territory}"), var));
+        }
+        // <language>
+        result.push((lang, var));
+    }
+
+    result
+}
+
+/// Get languages from environment variables according to the tldr client specification.
+pub fn get_languages_from_env(out_vec: &mut Vec<String>) {
+    out_vec.extend(languages_from_env().into_iter().map(|(lang, _)| lang));
+}
+
+/// Add regional variants from `available` after every language without a territory
+/// (e.g. `pt_BR` and `pt_PT` after `pt`), unless they are already in `languages`.
+pub fn add_language_variants<S>(languages: &[String], available: &[S]) -> Vec<String>
+where
+    S: AsRef<str>,
+{
+    let mut result = vec![];
 
     for lang in languages {
-        if lang.len() >= 5 && lang.chars().nth(2) == Some('_') {
-            // <language>_<country> (ll_CC - 5 characters)
-            out_vec.push(lang[..5].to_string());
-            // <language> (ll - 2 characters)
-            out_vec.push(lang[..2].to_string());
-        } else if lang.len() == 2 {
-            out_vec.push(lang.to_string());
+        result.push(lang.clone());
+
+        if lang.contains('_') {
+            continue;
+        }
+
+        let prefix = format!("{lang}_");
+        let mut variants: Vec<&str> = available
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|x| x.starts_with(&prefix) && !languages.iter().any(|l| l == x))
+            .collect();
+        variants.sort_unstable();
+        result.extend(variants.into_iter().map(str::to_string));
+    }
+
+    result.dedup_nosort();
+    result
+}
+
+/// The platform of the system tldr is running on, detected at runtime.
+pub struct DetectedPlatform {
+    /// The platform to search first.
+    pub primary: &'static str,
+    /// The platform to search after `primary` (e.g. `windows` under WSL).
+    pub secondary: Option<&'static str>,
+    /// What the platform was detected from.
+    pub source: &'static str,
+}
+
+impl DetectedPlatform {
+    /// Detect the platform from the operating system, `/proc/version` and environment variables.
+    pub fn detect() -> Self {
+        let proc_version = std::fs::read_to_string("/proc/version").ok();
+
+        Self::from_system(env::consts::OS, proc_version.as_deref(), |var| {
+            env::var_os(var).is_some_and(|x| !x.is_empty())
+        })
+    }
+
+    fn from_system<F>(os: &str, proc_version: Option<&str>, has_var: F) -> Self
+    where
+        F: Fn(&str) -> bool,
+    {
+        // Termux also sets the Android variables, this is only used to tell them apart.
+        let termux = has_var("TERMUX_VERSION");
+        let android = os == "android" || has_var("ANDROID_ROOT") && has_var("ANDROID_DATA");
+        let wsl = has_var("WSL_DISTRO_NAME")
+            || proc_version.is_some_and(|v| v.to_lowercase().contains("microsoft"));
+
+        let (primary, secondary, source) = match os {
+            "linux" | "android" if termux => ("android", Some("linux"), "Termux"),
+            "linux" | "android" if android => ("android", Some("linux"), "Android"),
+            "linux" if wsl => ("linux", Some("windows"), "WSL"),
+            "linux" => ("linux", None, "Linux"),
+            "macos" => ("osx", None, "macOS"),
+            "windows" => ("windows", None, "Windows"),
+            "freebsd" => ("freebsd", None, "FreeBSD"),
+            // DragonFly BSD is a fork of FreeBSD and has no pages of its own.
+            "dragonfly" => ("freebsd", None, "DragonFly BSD"),
+            "openbsd" => ("openbsd", None, "OpenBSD"),
+            "netbsd" => ("netbsd", None, "NetBSD"),
+            "solaris" | "illumos" => ("sunos", None, "SunOS"),
+            _ => ("common", None, "unknown operating system"),
+        };
+
+        Self {
+            primary,
+            secondary,
+            source,
         }
     }
+
+    /// Get the detected platforms in the order they should be searched.
+    pub fn platforms(&self) -> Vec<String> {
+        iter::once(self.primary)
+            .chain(self.secondary)
+            .map(str::to_string)
+            .collect()
+    }
 }
 
 /// Initialize color outputting.
@@ -126,35 +260,73 @@ where
 }
 
 pub trait PagePathExt {
-    /// Extracts the page name from its path.
-    fn page_name(&self) -> Option<Cow<str>>;
     /// Extracts the platform from the page path.
-    fn page_platform(&self) -> Option<Cow<str>>;
+    fn page_platform(&self) -> Option<Cow<'_, str>>;
 }
 
 impl PagePathExt for Path {
-    fn page_name(&self) -> Option<Cow<str>> {
-        self.file_stem().map(OsStr::to_string_lossy)
-    }
-
-    fn page_platform(&self) -> Option<Cow<str>> {
+    fn page_platform(&self) -> Option<Cow<'_, str>> {
         self.parent()
             .and_then(|parent| parent.file_name().map(OsStr::to_string_lossy))
     }
 }
 
-/// Calculates the SHA256 hash and returns a hexadecimal string.
-pub fn sha256_hexdigest(data: &[u8]) -> String {
-    let digest = digest(&SHA256, data);
-    let mut hex = String::new();
+/// A writer that calculates the SHA256 hash of all data written through it.
+pub struct Sha256Writer<W> {
+    inner: W,
+    ctx: Context,
+}
 
-    for part in digest.as_ref() {
-        hex += &format!("{part:02x}");
+impl<W: Write> Sha256Writer<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            ctx: Context::new(&SHA256),
+        }
+    }
+
+    /// Return the inner writer and the hash as a hexadecimal string.
+    pub fn finish(self) -> (W, String) {
+        (self.inner, hex_encode(self.ctx.finish().as_ref()))
+    }
+}
+
+impl<W: Write> Write for Sha256Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.ctx.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Encodes bytes as a lowercase hexadecimal string.
+pub fn hex_encode(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(data.len() * 2);
+
+    for part in data {
+        let _ = write!(hex, "{part:02x}");
     }
 
     hex
 }
 
+/// Decodes a hexadecimal string. Returns `None` if the string is not valid hex.
+pub fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    // `from_str_radix` accepts a leading `+`, which is not valid hex.
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 const DAY: u64 = 86400;
 const HOUR: u64 = 3600;
 const MINUTE: u64 = 60;
@@ -191,12 +363,89 @@ pub fn duration_fmt(secs: u64) -> String {
     }
 }
 
+/// Calculate the Levenshtein distance between two strings (in characters).
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+
+        mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+/// Convert a date in the proleptic Gregorian calendar to days since the Unix epoch.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Years start in March, so that the leap day is at the end of a year.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Convert days since the Unix epoch to a `(year, month, day)` date.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Parse a `YYYY-MM-DD` date (UTC) into seconds since the Unix epoch.
+pub fn parse_date(s: &str) -> Option<u64> {
+    let mut spl = s.splitn(3, '-');
+    let mut next = || spl.next()?.parse::<i64>().ok();
+    let (year, month, day) = (next()?, next()?, next()?);
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    // Reject days that do not exist in the month (e.g. 2023-02-29).
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+
+    u64::try_from(days).ok().map(|d| d * DAY)
+}
+
+/// Format seconds since the Unix epoch as a `YYYY-MM-DD` date (UTC).
+pub fn date_fmt(secs: u64) -> String {
+    #[allow(clippy::cast_possible_wrap)]
+    let (year, month, day) = civil_from_days((secs / DAY) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::env;
 
     fn prepare_env(lang: Option<&str>, language: Option<&str>) {
+        env::remove_var("LC_ALL");
+        env::remove_var("LC_MESSAGES");
+
         if let Some(lang) = lang {
             env::set_var("LANG", lang);
         } else {
@@ -245,16 +494,119 @@ mod tests {
         out_vec.clear();
         get_languages_from_env(&mut out_vec);
         assert_eq!(out_vec, ["de_DE", "de", "pl", "en", "en_US", "en"]);
+
+        // LC_ALL and LC_MESSAGES take precedence over LANG.
+        prepare_env(Some("en_US.UTF-8"), None);
+        env::set_var("LC_MESSAGES", "pt_PT.UTF-8@euro");
+        out_vec.clear();
+        get_languages_from_env(&mut out_vec);
+        assert_eq!(out_vec, ["pt_PT", "pt"]);
+
+        env::set_var("LC_ALL", "de_AT");
+        out_vec.clear();
+        get_languages_from_env(&mut out_vec);
+        assert_eq!(out_vec, ["de_AT", "de"]);
+
+        // LANGUAGE is ignored in the C locale.
+        prepare_env(Some("C.UTF-8"), Some("de:pl"));
+        out_vec.clear();
+        get_languages_from_env(&mut out_vec);
+        assert!(out_vec.is_empty());
+
+        prepare_env(Some("POSIX"), None);
+        out_vec.clear();
+        get_languages_from_env(&mut out_vec);
+        assert!(out_vec.is_empty());
+
+        prepare_env(Some("fil_PH.UTF-8"), Some("zh-Hant:sr_RS@latin:invalid"));
+        out_vec.clear();
+        get_languages_from_env(&mut out_vec);
+        assert_eq!(out_vec, ["zh_TW", "zh", "sr_RS", "sr", "fil_PH", "fil"]);
+
+        let languages = languages_from_env();
+        assert_eq!(languages.first(), Some(&("zh_TW".to_string(), "LANGUAGE")));
+        assert_eq!(languages.last(), Some(&("fil".to_string(), "LANG")));
+    }
+
+    #[test]
+    fn language_variants() {
+        let available = ["de", "en", "pt_BR", "pt_PT", "zh", "zh_TW"];
+        let languages = ["pt_PT".to_string(), "pt".to_string(), "en".to_string()];
+        assert_eq!(
+            add_language_variants(&languages, &available),
+            ["pt_PT", "pt", "pt_BR", "en"]
+        );
+
+        let languages = ["zh".to_string(), "de_AT".to_string(), "de".to_string()];
+        assert_eq!(
+            add_language_variants(&languages, &available),
+            ["zh", "zh_TW", "de_AT", "de"]
+        );
+    }
+
+    #[test]
+    fn detect_platform() {
+        let detect = |os, proc_version, vars: &[&str]| {
+            let p = DetectedPlatform::from_system(os, proc_version, |var| vars.contains(&var));
+            (p.platforms(), p.source)
+        };
+
+        let wsl = "Linux version 5.15.90.1-microsoft-standard-WSL2 (gcc (GCC) 11.2.0)";
+        assert_eq!(
+            detect("linux", Some(wsl), &[]),
+            (vec!["linux".into(), "windows".into()], "WSL")
+        );
+        assert_eq!(
+            detect("linux", Some("Linux version 6.1.0"), &[]).0,
+            ["linux"]
+        );
+        assert_eq!(
+            detect("linux", None, &["WSL_DISTRO_NAME"]).0,
+            ["linux", "windows"]
+        );
+
+        let termux = detect(
+            "linux",
+            None,
+            &["TERMUX_VERSION", "ANDROID_ROOT", "ANDROID_DATA"],
+        );
+        assert_eq!(termux, (vec!["android".into(), "linux".into()], "Termux"));
+        assert_eq!(
+            detect("linux", None, &["ANDROID_ROOT", "ANDROID_DATA"]).1,
+            "Android"
+        );
+        assert_eq!(detect("linux", None, &["ANDROID_ROOT"]).0, ["linux"]);
+        assert_eq!(detect("android", None, &[]).0, ["android", "linux"]);
+
+        assert_eq!(detect("macos", None, &[]).0, ["osx"]);
+        assert_eq!(detect("dragonfly", None, &[]).0, ["freebsd"]);
+        assert_eq!(detect("openbsd", None, &[]).0, ["openbsd"]);
+        assert_eq!(detect("haiku", None, &[]).0, ["common"]);
     }
 
     #[test]
     fn sha256() {
+        let mut writer = Sha256Writer::new(vec![]);
+        writer.write_all(b"This is a test.").unwrap();
+        let (data, hash) = writer.finish();
+
+        assert_eq!(data, b"This is a test.");
         assert_eq!(
-            sha256_hexdigest(b"This is a test."),
+            hash,
             "a8a2f6ebe286697c527eb35a58b5539532e9b3ae3b64d4eb0a46fb657b41562c"
         );
     }
 
+    #[test]
+    fn hex() {
+        assert_eq!(hex_encode(&[0, 15, 171, 255]), "000fabff");
+        assert_eq!(hex_decode("000fabff").unwrap(), [0, 15, 171, 255]);
+        assert_eq!(hex_decode("000FABFF").unwrap(), [0, 15, 171, 255]);
+        assert!(hex_decode("abc").is_none());
+        assert!(hex_decode("zz").is_none());
+        assert!(hex_decode("+1").is_none());
+    }
+
     #[test]
     fn dur_fmt() {
         const SECOND: u64 = 1;
@@ -274,4 +626,31 @@ mod tests {
         assert_eq!(duration_fmt(DAY + HOUR), "1d, 1h");
         assert_eq!(duration_fmt(DAY + HOUR + SECOND), "1d, 1h");
     }
+
+    #[test]
+    fn edit_dist() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("tar", ""), 3);
+        assert_eq!(edit_distance("", "tar"), 3);
+        assert_eq!(edit_distance("docker", "docker"), 0);
+        assert_eq!(edit_distance("dockr", "docker"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("gti", "git"), 2);
+        assert_eq!(edit_distance("żółw", "zolw"), 3);
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-03-01"), Some(951_868_800));
+        assert_eq!(parse_date("2024-02-29"), Some(1_709_164_800));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("1969-12-31"), None);
+        assert_eq!(parse_date("yesterday"), None);
+
+        assert_eq!(date_fmt(0), "1970-01-01");
+        assert_eq!(date_fmt(951_868_800 + DAY - 1), "2000-03-01");
+        assert_eq!(date_fmt(1_709_164_800), "2024-02-29");
+    }
 }
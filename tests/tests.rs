@@ -1,7 +1,20 @@
+use std::fmt::Write as _;
 use std::fs;
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
+use assert_cmd::assert::Assert;
 use assert_cmd::prelude::*;
+use ring::digest::{digest, SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
 
 const TEST_PAGE: &str = "tests/data/page.md";
 const TEST_PAGE_RENDER: &str = "tests/data/page-render";
@@ -47,3 +60,1212 @@ fn does_not_exist() {
         .assert()
         .failure();
 }
+
+/// Create a local mirror with English and German pages in a temporary directory
+/// and return the path to a config file that uses it.
+fn local_mirror(test_name: &str) -> PathBuf {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join(test_name);
+    let _ = fs::remove_dir_all(&root);
+    let mirror = root.join("mirror");
+    fs::create_dir_all(&mirror).unwrap();
+
+    let page = fs::read_to_string(TEST_PAGE).unwrap();
+    let mut sums = String::new();
+
+    for (lang, pages) in [
+        ("en", &["common/ls.md", "common/tar.md", "linux/apt.md"][..]),
+        ("de", &["common/tar.md"][..]),
+    ] {
+        let fname = format!("tldr-pages.{lang}.zip");
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+
+        zip.start_file("LICENSE.md", FileOptions::default())
+            .unwrap();
+        for page_path in pages {
+            zip.start_file(*page_path, FileOptions::default()).unwrap();
+            zip.write_all(page.as_bytes()).unwrap();
+        }
+
+        let data = zip.finish().unwrap().into_inner();
+        fs::write(mirror.join(&fname), &data).unwrap();
+        writeln!(sums, "{}  {fname}", sha256_hexdigest(&data)).unwrap();
+    }
+
+    fs::write(mirror.join("tldr.sha256sums"), sums).unwrap();
+
+    let config = root.join("config.toml");
+    fs::write(
+        &config,
+        format!(
+            "[cache]\n\
+            dir = '{}'\n\
+            mirror = 'file://{}'\n\
+            languages = ['de', 'en']\n",
+            root.join("cache").display(),
+            mirror.display()
+        ),
+    )
+    .unwrap();
+
+    config
+}
+
+fn sha256_hexdigest(data: &[u8]) -> String {
+    digest(&SHA256, data)
+        .as_ref()
+        .iter()
+        .fold(String::new(), |mut hex, b| {
+            write!(hex, "{b:02x}").unwrap();
+            hex
+        })
+}
+
+/// Replace the sum of `fname` in `sumfile` with zeros.
+fn break_sum(sumfile: &Path, fname: &str) {
+    let sums: String = fs::read_to_string(sumfile)
+        .unwrap()
+        .lines()
+        .map(|l| {
+            if l.ends_with(fname) {
+                format!("{}  {fname}\n", "0".repeat(64))
+            } else {
+                format!("{l}\n")
+            }
+        })
+        .collect();
+    fs::write(sumfile, sums).unwrap();
+}
+
+fn tlrc_with_config(config: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("tldr").unwrap();
+    cmd.arg("--config").arg(config);
+    cmd
+}
+
+/// Serve files from `dir` over HTTP on a local port, with their SHA256 sums as `ETag` headers.
+///
+/// Returns the URL of the server and a log of the requests:
+/// `<path>` or `<path> (If-None-Match: <etag>)` for conditional requests.
+fn http_mirror(dir: &Path) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let log = Arc::new(Mutex::new(vec![]));
+    let (dir, server_log) = (dir.to_path_buf(), log.clone());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let path = request.split(' ').nth(1).unwrap().to_string();
+
+            let mut if_none_match = None;
+            for l in (&mut reader).lines() {
+                let l = l.unwrap();
+                if l.is_empty() {
+                    break;
+                }
+                if let Some((k, v)) = l.split_once(": ") {
+                    if k.eq_ignore_ascii_case("if-none-match") {
+                        if_none_match = Some(v.to_string());
+                    }
+                }
+            }
+
+            server_log.lock().unwrap().push(match &if_none_match {
+                Some(etag) => format!("{path} (If-None-Match: {etag})"),
+                None => path.clone(),
+            });
+
+            let (status, etag, body) = match fs::read(dir.join(path.trim_start_matches('/'))) {
+                Ok(data) => {
+                    let etag = format!("\"{}\"", sha256_hexdigest(&data));
+                    if if_none_match.as_ref() == Some(&etag) {
+                        ("304 Not Modified", etag, vec![])
+                    } else {
+                        ("200 OK", etag, data)
+                    }
+                }
+                Err(_) => ("404 Not Found", String::new(), vec![]),
+            };
+            let mut response = format!(
+                "HTTP/1.1 {status}\r\nConnection: close\r\nETag: {etag}\r\n\
+                Content-Length: {}\r\n\r\n",
+                body.len()
+            )
+            .into_bytes();
+            response.extend(body);
+
+            // Every connection is closed after the response.
+            let _ = stream.write_all(&response);
+        }
+    });
+
+    (url, log)
+}
+
+/// Append `lines` to the `[cache]` section of `config`.
+fn add_to_config(config: &Path, lines: &str) {
+    let mut cfg = fs::read_to_string(config).unwrap();
+    cfg.push_str(lines);
+    fs::write(config, cfg).unwrap();
+}
+
+/// Write `(path, contents)` pairs to files in `dir`.
+fn write_pages(dir: &Path, pages: &[(&str, &str)]) {
+    for (page_path, contents) in pages {
+        let path = dir.join(page_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+/// Set up a local mirror with a custom pages directory that contains `pages`,
+/// and update the cache. Returns the path to the config file.
+fn local_mirror_with_custom_pages(test_name: &str, pages: &[(&str, &str)]) -> PathBuf {
+    let config = local_mirror(test_name);
+    let custom = config.parent().unwrap().join("custom");
+
+    write_pages(&custom, pages);
+    add_to_config(
+        &config,
+        &format!("custom_pages_dir = '{}'\n", custom.display()),
+    );
+    tlrc_with_config(&config).arg("--update").assert().success();
+
+    config
+}
+
+fn stdout_of(assert: &Assert) -> String {
+    String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+}
+
+fn stderr_of(assert: &Assert) -> String {
+    String::from_utf8(assert.get_output().stderr.clone()).unwrap()
+}
+
+#[test]
+fn update_from_local_mirror() {
+    let config = local_mirror("update_from_local_mirror");
+    let cache = config.parent().unwrap().join("cache");
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    assert!(cache.join("pages.en/common/tar.md").is_file());
+    assert!(cache.join("pages.en/linux/apt.md").is_file());
+    assert!(cache.join("pages.de/common/tar.md").is_file());
+    assert!(cache.join("tldr.sha256sums").is_file());
+
+    let expected = fs::read_to_string(TEST_PAGE_RENDER).unwrap();
+    tlrc_with_config(&config)
+        .args(["--offline", "--language", "de", "tar"])
+        .assert()
+        .stdout(expected);
+}
+
+#[test]
+fn update_not_modified() {
+    let config = local_mirror("update_not_modified");
+    let root = config.parent().unwrap();
+    let cache = root.join("cache");
+    let (url, log) = http_mirror(&root.join("mirror"));
+    let cfg = fs::read_to_string(&config).unwrap();
+    let file_mirror = format!("file://{}", root.join("mirror").display());
+    fs::write(&config, cfg.replace(&file_mirror, &url)).unwrap();
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "/tldr.sha256sums",
+            "/tldr-pages.de.zip",
+            "/tldr-pages.en.zip"
+        ]
+    );
+
+    let sums = fs::read(cache.join("tldr.sha256sums")).unwrap();
+    let etag = format!("\"{}\"", sha256_hexdigest(&sums));
+    let headers = fs::read_to_string(cache.join("tldr.sha256sums.headers")).unwrap();
+    assert_eq!(headers, format!("etag {url}: {etag}\nlanguages: de en\n"));
+
+    // Make the cache look old. Archives would fail to download if they were requested.
+    let sumfile = fs::File::options()
+        .write(true)
+        .open(cache.join("tldr.sha256sums"))
+        .unwrap();
+    let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    sumfile.set_modified(old).unwrap();
+    fs::remove_file(root.join("mirror/tldr-pages.en.zip")).unwrap();
+    log.lock().unwrap().clear();
+
+    let stderr = stderr_of(&tlrc_with_config(&config).arg("--update").assert().success());
+    assert!(stderr.contains("'tldr.sha256sums' has not changed"));
+    // Only the checksum file was requested, and the server responded with 304 Not Modified.
+    assert_eq!(
+        *log.lock().unwrap(),
+        [format!("/tldr.sha256sums (If-None-Match: {etag})")]
+    );
+
+    // The age of the cache is refreshed, the checksum file and its headers are kept.
+    let modified = fs::metadata(cache.join("tldr.sha256sums"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert!(modified > old);
+    assert_eq!(fs::read(cache.join("tldr.sha256sums")).unwrap(), sums);
+    assert_eq!(
+        fs::read_to_string(cache.join("tldr.sha256sums.headers")).unwrap(),
+        headers
+    );
+    assert!(cache.join("pages.en/common/tar.md").is_file());
+}
+
+#[test]
+fn update_sum_mismatch() {
+    let config = local_mirror("update_sum_mismatch");
+    let root = config.parent().unwrap();
+    let cache = root.join("cache");
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+
+    // Change the sum of the English archive. The update should fail and leave the old pages intact.
+    break_sum(&root.join("mirror/tldr.sha256sums"), "tldr-pages.en.zip");
+
+    tlrc_with_config(&config)
+        .arg("--update")
+        .assert()
+        .failure()
+        .code(4);
+    assert!(cache.join("pages.en/common/tar.md").is_file());
+    assert!(cache.join("pages.de/common/tar.md").is_file());
+    assert!(cache.join("pages.en/linux/apt.md").is_file());
+    // No staging directories should be left behind.
+    assert!(!fs::read_dir(&cache).unwrap().any(|e| e
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .starts_with(".staging.")));
+}
+
+#[test]
+fn update_fallback_mirror() {
+    let config = local_mirror("update_fallback_mirror");
+    let root = config.parent().unwrap();
+
+    let cfg = fs::read_to_string(&config).unwrap().replace(
+        "mirror = 'file://",
+        "mirror = ['/nonexistent/mirror', 'file://",
+    );
+    fs::write(&config, cfg.replace("/mirror'\n", "/mirror']\n")).unwrap();
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    assert!(root.join("cache/pages.en/common/tar.md").is_file());
+
+    // Errors from every mirror should be reported.
+    fs::remove_dir_all(root.join("mirror")).unwrap();
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .arg("--update")
+            .assert()
+            .failure()
+            .code(4),
+    );
+
+    assert!(stderr.contains("'/nonexistent/mirror/tldr.sha256sums'"));
+    assert!(stderr.contains(&format!(
+        "'{}'",
+        root.join("mirror/tldr.sha256sums").display()
+    )));
+}
+
+#[test]
+fn update_signed_sums() {
+    let config = local_mirror("update_signed_sums");
+    let root = config.parent().unwrap();
+    let sig = root.join("mirror/tldr.sha256sums.sig");
+
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+    let sums = fs::read(root.join("mirror/tldr.sha256sums")).unwrap();
+    fs::write(&sig, key.sign(&sums)).unwrap();
+
+    let public_key = key
+        .public_key()
+        .as_ref()
+        .iter()
+        .fold(String::new(), |mut hex, b| {
+            write!(hex, "{b:02x}").unwrap();
+            hex
+        });
+    add_to_config(&config, &format!("public_keys = ['{public_key}']\n"));
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    assert!(root.join("cache/pages.en/common/tar.md").is_file());
+
+    // A signature made by another key should be rejected.
+    let other_pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    let other_key = Ed25519KeyPair::from_pkcs8(other_pkcs8.as_ref()).unwrap();
+    fs::write(&sig, other_key.sign(&sums)).unwrap();
+
+    tlrc_with_config(&config)
+        .arg("--update")
+        .assert()
+        .failure()
+        .code(4);
+
+    // A missing signature should be rejected too.
+    fs::remove_file(&sig).unwrap();
+
+    tlrc_with_config(&config)
+        .arg("--update")
+        .assert()
+        .failure()
+        .code(4);
+}
+
+#[test]
+fn import_archive() {
+    let config = local_mirror("import_archive");
+    let root = config.parent().unwrap();
+    let archive = root.join("mirror/tldr-pages.de.zip");
+
+    tlrc_with_config(&config)
+        .arg("--import")
+        .arg(&archive)
+        .assert()
+        .success();
+    assert!(root.join("cache/pages.de/common/tar.md").is_file());
+
+    // The archive is verified using the checksum file next to it.
+    break_sum(&root.join("mirror/tldr.sha256sums"), "tldr-pages.de.zip");
+
+    tlrc_with_config(&config)
+        .arg("--import")
+        .arg(&archive)
+        .assert()
+        .failure()
+        .code(4);
+}
+
+#[test]
+fn export_and_import_bundle() {
+    let config = local_mirror("export_and_import_bundle");
+    let root = config.parent().unwrap();
+    let bundle = root.join("bundle.zip");
+    let other_cache = root.join("other-cache");
+    let other_config = root.join("other.toml");
+    let cfg = fs::read_to_string(&config).unwrap();
+    fs::write(&other_config, cfg.replace("/cache'", "/other-cache'")).unwrap();
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    tlrc_with_config(&config)
+        .arg("--export")
+        .arg(&bundle)
+        .assert()
+        .success();
+
+    // Import the bundle into an empty cache. Extracted pages cannot be verified,
+    // so their sums are not kept.
+    tlrc_with_config(&other_config)
+        .arg("--import")
+        .arg(&bundle)
+        .assert()
+        .success();
+
+    assert!(other_cache.join("pages.en/linux/apt.md").is_file());
+    assert!(other_cache.join("pages.de/common/tar.md").is_file());
+    assert_eq!(
+        fs::read_to_string(other_cache.join("tldr.sha256sums")).unwrap(),
+        ""
+    );
+
+    // Archives are exported unchanged and verified on import.
+    add_to_config(&config, "storage = 'zip'\n");
+    tlrc_with_config(&config).arg("--update").assert().success();
+    tlrc_with_config(&config)
+        .arg("--export")
+        .arg(&bundle)
+        .assert()
+        .success();
+    fs::remove_dir_all(&other_cache).unwrap();
+
+    tlrc_with_config(&other_config)
+        .arg("--import")
+        .arg(&bundle)
+        .assert()
+        .success();
+
+    assert!(other_cache.join("pages.en/linux/apt.md").is_file());
+    assert!(other_cache.join("pages.de/common/tar.md").is_file());
+    let mut sums: Vec<String> = fs::read_to_string(other_cache.join("tldr.sha256sums"))
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let mut expected: Vec<String> = fs::read_to_string(root.join("cache/tldr.sha256sums"))
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    sums.sort_unstable();
+    expected.sort_unstable();
+    assert_eq!(sums, expected);
+
+    // A bundle with an archive that does not match its sum is rejected.
+    let mut archive = ZipArchive::new(fs::File::open(&bundle).unwrap()).unwrap();
+    let tampered = root.join("tampered.zip");
+    let mut zip = ZipWriter::new(fs::File::create(&tampered).unwrap());
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).unwrap();
+        if file.name() == "tldr.sha256sums" {
+            continue;
+        }
+        zip.raw_copy_file(file).unwrap();
+    }
+    zip.start_file("tldr.sha256sums", FileOptions::default())
+        .unwrap();
+    for fname in ["tldr-pages.de.zip", "tldr-pages.en.zip"] {
+        writeln!(zip, "{}  {fname}", "0".repeat(64)).unwrap();
+    }
+    zip.finish().unwrap();
+    fs::remove_dir_all(&other_cache).unwrap();
+
+    let stderr = stderr_of(
+        &tlrc_with_config(&other_config)
+            .arg("--import")
+            .arg(&tampered)
+            .assert()
+            .failure()
+            .code(4),
+    );
+    assert!(stderr.contains("SHA256 sum mismatch"));
+    assert!(!other_cache.join("pages.de").exists());
+}
+
+#[test]
+fn import_rejects_unsafe_entries() {
+    let config = local_mirror("import_rejects_unsafe_entries");
+    let root = config.parent().unwrap();
+    let archive = root.join("tldr-pages.de.zip");
+
+    for (name, symlink) in [
+        ("common/../../evil.md", false),
+        ("/tmp/evil.md", false),
+        ("common/evil.md", true),
+    ] {
+        let mut zip = ZipWriter::new(fs::File::create(&archive).unwrap());
+        if symlink {
+            zip.add_symlink(name, "/etc/passwd", FileOptions::default())
+                .unwrap();
+        } else {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(b"evil").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let stderr = stderr_of(
+            &tlrc_with_config(&config)
+                .arg("--import")
+                .arg(&archive)
+                .assert()
+                .failure()
+                .code(4),
+        );
+
+        assert!(stderr.contains(&format!("'{name}'")));
+        assert!(!root.join("cache/pages.de").exists());
+    }
+
+    // Pages in the full archive must stay in their language directory.
+    let archive = root.join("tldr.zip");
+    let mut zip = ZipWriter::new(fs::File::create(&archive).unwrap());
+    zip.start_file(
+        "pages.de/../pages.en/common/evil.md",
+        FileOptions::default(),
+    )
+    .unwrap();
+    zip.finish().unwrap();
+
+    tlrc_with_config(&config)
+        .arg("--import")
+        .arg(&archive)
+        .assert()
+        .failure()
+        .code(4);
+    assert!(!root.join("cache/pages.en").exists());
+}
+
+#[test]
+fn update_archive_limits() {
+    let config = local_mirror("update_archive_limits");
+    let root = config.parent().unwrap();
+    let cache = root.join("cache");
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    let old_page = fs::read_to_string(cache.join("pages.de/common/tar.md")).unwrap();
+
+    // Replace the German archive with one that contains a 2 KiB page.
+    let archive = root.join("mirror/tldr-pages.de.zip");
+    let old_sum = sha256_hexdigest(&fs::read(&archive).unwrap());
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    zip.start_file("common/tar.md", FileOptions::default())
+        .unwrap();
+    zip.write_all(&[b'x'; 2048]).unwrap();
+    let data = zip.finish().unwrap().into_inner();
+    fs::write(&archive, &data).unwrap();
+
+    let sumfile = root.join("mirror/tldr.sha256sums");
+    let sums = fs::read_to_string(&sumfile).unwrap();
+    fs::write(&sumfile, sums.replace(&old_sum, &sha256_hexdigest(&data))).unwrap();
+
+    let cfg = fs::read_to_string(&config).unwrap();
+
+    for limit in [
+        "max_page_size = 1",
+        "max_extracted_size = 0",
+        "max_archive_entries = 0",
+    ] {
+        fs::write(&config, format!("{cfg}{limit}\n")).unwrap();
+
+        let stderr = stderr_of(
+            &tlrc_with_config(&config)
+                .arg("--update")
+                .assert()
+                .failure()
+                .code(4),
+        );
+
+        let option = limit.split(' ').next().unwrap();
+        assert!(stderr.contains(option));
+        // The old pages should be left intact.
+        assert_eq!(
+            fs::read_to_string(cache.join("pages.de/common/tar.md")).unwrap(),
+            old_page
+        );
+    }
+
+    fs::write(&config, cfg).unwrap();
+    tlrc_with_config(&config).arg("--update").assert().success();
+    assert_eq!(
+        fs::read(cache.join("pages.de/common/tar.md")).unwrap(),
+        [b'x'; 2048]
+    );
+}
+
+#[test]
+fn zip_storage() {
+    let config = local_mirror("zip_storage");
+    let root = config.parent().unwrap();
+    let cache = root.join("cache");
+    let cfg = fs::read_to_string(&config).unwrap();
+    add_to_config(&config, "storage = 'zip'\n");
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    assert!(cache.join("tldr-pages.en.zip").is_file());
+    assert!(cache.join("tldr-pages.de.zip").is_file());
+    assert!(!cache.join("pages.en").exists());
+
+    let expected = fs::read_to_string(TEST_PAGE_RENDER).unwrap();
+    tlrc_with_config(&config)
+        .args(["--offline", "--language", "de", "tar"])
+        .assert()
+        .stdout(expected);
+    tlrc_with_config(&config)
+        .args(["--offline", "--list-all"])
+        .assert()
+        .stdout("apt\nls\ntar\n");
+    tlrc_with_config(&config)
+        .args(["--offline", "--list-languages"])
+        .assert()
+        .stdout("de\nen\n");
+
+    // Pages read from archives are limited like extracted ones.
+    add_to_config(&config, "max_page_size = 0\n");
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "tar"])
+            .assert()
+            .failure(),
+    );
+    assert!(stderr.contains("max_page_size"));
+
+    // Switching back to directories should replace the archives.
+    fs::write(&config, cfg).unwrap();
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    assert!(cache.join("pages.en/common/tar.md").is_file());
+    assert!(!cache.join("tldr-pages.en.zip").exists());
+}
+
+#[test]
+fn page_index() {
+    let config = local_mirror("page_index");
+    let root = config.parent().unwrap();
+    let cache = root.join("cache");
+    let index = cache.join("tldr.index");
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    let contents = fs::read_to_string(&index).unwrap();
+    assert!(contents.contains("\nen\tlinux\t"));
+    assert!(contents.contains("\nde\tcommon\t"));
+    assert!(contents.contains("\napt\t"));
+
+    // Listing should use the index.
+    let with_fake = contents.replace("\napt\t", "\nfak\t");
+    fs::write(&index, &with_fake).unwrap();
+    tlrc_with_config(&config)
+        .args(["--offline", "--list"])
+        .assert()
+        .stdout("fak\nls\ntar\n");
+
+    // The index is only rebuilt when pages have changed.
+    tlrc_with_config(&config).arg("--update").assert().success();
+    assert_eq!(fs::read_to_string(&index).unwrap(), with_fake);
+
+    // A stale index (built for different pages) should be ignored.
+    let sumfile = cache.join("tldr.sha256sums");
+    let sums = fs::read_to_string(&sumfile).unwrap();
+    fs::write(&sumfile, format!("{sums}\n")).unwrap();
+    tlrc_with_config(&config)
+        .args(["--offline", "--list"])
+        .assert()
+        .stdout("apt\nls\ntar\n");
+    fs::write(&sumfile, sums).unwrap();
+
+    // Lookups check the pages directly, 'apt' is not in the index.
+    tlrc_with_config(&config)
+        .args(["--offline", "--raw", "apt"])
+        .assert()
+        .success();
+    fs::remove_file(cache.join("pages.en/linux/apt.md")).unwrap();
+
+    // Without the index, the directories should be scanned.
+    fs::remove_file(&index).unwrap();
+    tlrc_with_config(&config)
+        .args(["--offline", "--list"])
+        .assert()
+        .stdout("ls\ntar\n");
+    let expected = fs::read_to_string(TEST_PAGE_RENDER).unwrap();
+    tlrc_with_config(&config)
+        .args(["--offline", "--language", "de", "tar"])
+        .assert()
+        .stdout(expected);
+
+    // A missing index is rebuilt by the next update.
+    tlrc_with_config(&config).arg("--update").assert().success();
+    let contents = fs::read_to_string(&index).unwrap();
+    assert!(contents.contains("\nls\t"));
+    assert!(!contents.contains("\napt\t"));
+}
+
+#[test]
+fn verify_cache() {
+    let config = local_mirror("verify_cache");
+    let root = config.parent().unwrap();
+    let cache = root.join("cache");
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    tlrc_with_config(&config)
+        .arg("--verify-cache")
+        .assert()
+        .success()
+        .stdout("pages.de: OK\npages.en: OK\n");
+
+    let tar = fs::read(cache.join("pages.en/common/tar.md")).unwrap();
+    fs::remove_file(cache.join("pages.en/common/tar.md")).unwrap();
+    fs::write(cache.join("pages.en/linux/apt.md"), "modified").unwrap();
+    fs::write(cache.join("pages.de/common/extra.md"), "extra").unwrap();
+
+    let stdout = stdout_of(
+        &tlrc_with_config(&config)
+            .arg("--verify-cache")
+            .assert()
+            .failure(),
+    );
+    assert!(stdout.contains("unexpected: pages.de/common/extra.md"));
+    assert!(stdout.contains("modified: pages.en/linux/apt.md"));
+    assert!(stdout.contains("missing: pages.en/common/tar.md"));
+
+    tlrc_with_config(&config)
+        .args(["--verify-cache", "--repair"])
+        .assert()
+        .success();
+    assert_eq!(fs::read(cache.join("pages.en/common/tar.md")).unwrap(), tar);
+    assert!(!cache.join("pages.de/common/extra.md").exists());
+
+    tlrc_with_config(&config)
+        .arg("--verify-cache")
+        .assert()
+        .success();
+
+    // Intact archives in the cache are used for repairs without the mirror.
+    add_to_config(&config, "storage = 'zip'\n");
+    tlrc_with_config(&config).arg("--update").assert().success();
+    write_pages(&cache, &[("pages.de/common/tar.md", "stray")]);
+    let mirror = root.join("mirror");
+    let offline_mirror = root.join("mirror.offline");
+    fs::rename(&mirror, &offline_mirror).unwrap();
+
+    tlrc_with_config(&config)
+        .args(["--verify-cache", "--repair"])
+        .assert()
+        .success();
+    assert!(!cache.join("pages.de").exists());
+    tlrc_with_config(&config)
+        .args(["--offline", "--raw", "--language", "de", "tar"])
+        .assert()
+        .success()
+        .stdout(fs::read_to_string(TEST_PAGE).unwrap());
+
+    // Damaged archives have to be downloaded again.
+    fs::write(cache.join("tldr-pages.de.zip"), "damaged").unwrap();
+    tlrc_with_config(&config)
+        .args(["--verify-cache", "--repair"])
+        .assert()
+        .failure();
+
+    fs::rename(&offline_mirror, &mirror).unwrap();
+    tlrc_with_config(&config)
+        .args(["--verify-cache", "--repair"])
+        .assert()
+        .success();
+    tlrc_with_config(&config)
+        .arg("--verify-cache")
+        .assert()
+        .success();
+}
+
+#[test]
+fn whats_new() {
+    let config = local_mirror("whats_new");
+    let mirror = config.parent().unwrap().join("mirror");
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+    tlrc_with_config(&config)
+        .arg("--whats-new")
+        .assert()
+        .success()
+        .stdout("");
+
+    // Add a page, remove one, change one and leave one untouched.
+    let page = fs::read_to_string(TEST_PAGE).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    for (page_path, contents) in [
+        ("common/deployctl.md", &*page),
+        ("common/tar.md", "# tar\n\n> Changed.\n"),
+        ("linux/apt.md", &*page),
+    ] {
+        zip.start_file(page_path, FileOptions::default()).unwrap();
+        zip.write_all(contents.as_bytes()).unwrap();
+    }
+    let data = zip.finish().unwrap().into_inner();
+    fs::write(mirror.join("tldr-pages.en.zip"), &data).unwrap();
+
+    let sumfile = mirror.join("tldr.sha256sums");
+    let sums = fs::read_to_string(&sumfile).unwrap();
+    let old_sum = &sums.lines().find(|l| l.ends_with("en.zip")).unwrap()[..64];
+    fs::write(&sumfile, sums.replace(old_sum, &sha256_hexdigest(&data))).unwrap();
+
+    // Fill the changelog, the oldest changes should be removed to make room for new ones.
+    let changes_file = config.parent().unwrap().join("cache").join("tldr.changes");
+    fs::write(&changes_file, "1\tadded\ten\tcommon\told\n".repeat(10_000)).unwrap();
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+
+    let changes_log = fs::read_to_string(&changes_file).unwrap();
+    assert_eq!(changes_log.lines().count(), 10_000);
+    assert!(changes_log
+        .lines()
+        .rev()
+        .take(3)
+        .all(|l| !l.starts_with("1\t")));
+
+    let stdout = stdout_of(
+        &tlrc_with_config(&config)
+            .args(["--whats-new", "--since", "2000-01-01"])
+            .assert()
+            .success(),
+    );
+    let (header, changes) = stdout.split_once('\n').unwrap();
+
+    assert!(header.ends_with(" (pages.en):"));
+    assert_eq!(
+        changes,
+        "  added   common/deployctl\n  removed common/ls\n  changed common/tar\n"
+    );
+
+    tlrc_with_config(&config)
+        .args(["--whats-new", "--since", "9999-01-01"])
+        .assert()
+        .success()
+        .stdout("");
+    tlrc_with_config(&config)
+        .args(["--whats-new", "--since", "yesterday"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn custom_pages() {
+    let config = local_mirror("custom_pages");
+    let root = config.parent().unwrap();
+    let first = root.join("custom1");
+    let second = root.join("custom2");
+
+    write_pages(
+        &first,
+        &[("common/deployctl.md", "# deployctl\n\n> First.\n")],
+    );
+    write_pages(
+        &second,
+        &[
+            ("common/deployctl.md", "# deployctl\n\n> Second.\n"),
+            ("common/tar.md", "# tar\n\n> Custom.\n"),
+            ("internal/vaultcli.md", "# vaultcli\n\n> Vault.\n"),
+        ],
+    );
+    add_to_config(
+        &config,
+        &format!(
+            "custom_pages_dir = ['{}', '{}']\n",
+            first.display(),
+            second.display()
+        ),
+    );
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+
+    // The first directory takes precedence, and custom pages override the cache.
+    for (page, expected) in [
+        ("deployctl", "# deployctl\n\n> First.\n"),
+        ("tar", "# tar\n\n> Custom.\n"),
+        ("vaultcli", "# vaultcli\n\n> Vault.\n"),
+    ] {
+        tlrc_with_config(&config)
+            .args(["--offline", "--raw", page])
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+
+    tlrc_with_config(&config)
+        .args(["--offline", "--list-all"])
+        .assert()
+        .stdout("apt\ndeployctl\nls\ntar\nvaultcli\n");
+    tlrc_with_config(&config)
+        .args(["--offline", "--platform", "internal", "--list"])
+        .assert()
+        .stdout("deployctl\nls\ntar\nvaultcli\n");
+
+    tlrc_with_config(&config)
+        .arg("--clean-cache")
+        .assert()
+        .success();
+    assert!(first.join("common/deployctl.md").is_file());
+    assert!(second.join("internal/vaultcli.md").is_file());
+}
+
+#[test]
+fn page_patches() {
+    let config = local_mirror_with_custom_pages(
+        "page_patches",
+        &[(
+            "common/tar.patch.md",
+            "# tar\n\n> Local examples.\n\n- Extract a release:\n\n`tar xf {{release.tar}}`\n",
+        )],
+    );
+
+    // Only the examples are appended to the upstream page.
+    let page = fs::read_to_string(TEST_PAGE).unwrap();
+    tlrc_with_config(&config)
+        .args(["--offline", "--raw", "tar"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{page}\n- Extract a release:\n\n`tar xf {{{{release.tar}}}}`\n"
+        ));
+
+    let stdout = stdout_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "tar"])
+            .assert()
+            .success(),
+    );
+    assert!(stdout.contains("Extract a release: (local)\n"));
+    assert!(!stdout.contains("Local examples."));
+
+    // Patches are not pages.
+    tlrc_with_config(&config)
+        .args(["--offline", "--list-all"])
+        .assert()
+        .stdout("apt\nls\ntar\n");
+    tlrc_with_config(&config)
+        .args(["--offline", "tar.patch"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn search() {
+    let config = local_mirror_with_custom_pages(
+        "search",
+        &[
+            (
+                "common/deployctl.md",
+                "# deployctl\n\n> Deploy services.\n\n\
+                - Deploy a test build:\n\n`deployctl push --test`\n",
+            ),
+            ("common/gross.md", "# gross\n\n> Print GROẞE letters.\n"),
+        ],
+    );
+
+    // Title matches come before example matches.
+    tlrc_with_config(&config)
+        .args(["--offline", "--search", "TEST"])
+        .assert()
+        .success()
+        .stdout(
+            "apt (linux, en): test page\n\
+            ls (common, en): test page\n\
+            tar (common, de): test page\n\
+            tar (common, en): test page\n\
+            deployctl (common, custom): Deploy a test build:\n",
+        );
+
+    // Pages have to contain all terms.
+    tlrc_with_config(&config)
+        .args(["--offline", "--search", "deploy", "test"])
+        .assert()
+        .success()
+        .stdout("deployctl (common, custom): deployctl\n");
+
+    // Lowercasing 'ẞ' changes its length, which must not break highlighting.
+    let stdout = stdout_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "--color", "always", "--search", "große"])
+            .assert()
+            .success(),
+    );
+    assert!(stdout.contains(": Print \x1b[1;33mGROẞE\x1b[0m letters.\n"));
+
+    tlrc_with_config(&config)
+        .args(["--offline", "--search", "nonexistent"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn suggest_similar_pages() {
+    let config = local_mirror_with_custom_pages(
+        "suggest_similar_pages",
+        &[("common/tar-extract.md", "# tar-extract\n")],
+    );
+
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "tarr"])
+            .assert()
+            .failure(),
+    );
+    assert!(stderr.contains("page not found. Did you mean: tar, tar-extract?"));
+
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "something"])
+            .assert()
+            .failure(),
+    );
+    assert!(stderr.contains("page not found. Try running"));
+
+    // Colors are not included in JSON errors.
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "--color", "always", "--json-errors", "tarr"])
+            .assert()
+            .code(1),
+    );
+    assert!(stderr.starts_with(
+        "{\"kind\":\"page_not_found\",\
+        \"message\":\"page not found. Did you mean: tar, tar-extract? "
+    ));
+    assert!(stderr.ends_with("\",\"suggestions\":[\"tar\",\"tar-extract\"]}\n"));
+    assert_eq!(stderr.lines().count(), 1);
+
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "--json-errors", "something"])
+            .assert()
+            .code(1),
+    );
+    assert!(stderr.ends_with("\",\"suggestions\":[]}\n"));
+}
+
+#[test]
+fn follow_aliases() {
+    let alias = |name: &str, target: &str| {
+        format!(
+            "# {name}\n\n> This command is an alias of `{target}`.\n\n\
+            - View documentation for the original command:\n\n`tldr {target}`\n"
+        )
+    };
+
+    let config = local_mirror_with_custom_pages(
+        "follow_aliases",
+        &[
+            ("common/gtar.md", &alias("gtar", "tar")),
+            ("common/first.md", &alias("first", "second")),
+            ("common/second.md", &alias("second", "first")),
+        ],
+    );
+
+    let page = fs::read_to_string(TEST_PAGE).unwrap();
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "--raw", "gtar"])
+            .assert()
+            .success()
+            .stdout(page),
+    );
+    assert!(stderr.contains("'gtar' is an alias of 'tar'"));
+
+    tlrc_with_config(&config)
+        .args(["--offline", "--raw", "--no-follow-aliases", "gtar"])
+        .assert()
+        .success()
+        .stdout(alias("gtar", "tar"));
+
+    // Cycles are detected and the last page before the cycle is shown.
+    tlrc_with_config(&config)
+        .args(["--offline", "--raw", "first"])
+        .assert()
+        .success()
+        .stdout(alias("second", "first"));
+}
+
+#[test]
+fn platform_order() {
+    let config = local_mirror_with_custom_pages(
+        "platform_order",
+        &[
+            ("linux/brew.md", "# brew\n\n> linux\n"),
+            ("osx/brew.md", "# brew\n\n> osx\n"),
+            ("windows/brew.md", "# brew\n\n> windows\n"),
+        ],
+    );
+    add_to_config(&config, "platform_order = ['windows', 'linux']\n");
+
+    tlrc_with_config(&config)
+        .args(["--offline", "--raw", "brew"])
+        .assert()
+        .success()
+        .stdout("# brew\n\n> windows\n");
+    tlrc_with_config(&config)
+        .args(["--offline", "--list"])
+        .assert()
+        .stdout("apt\nbrew\nls\ntar\n");
+
+    // --platform overrides the config and can be used multiple times.
+    tlrc_with_config(&config)
+        .args(["--offline", "--raw", "-p", "macos", "-p", "linux", "brew"])
+        .assert()
+        .success()
+        .stdout("# brew\n\n> osx\n");
+
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "--raw", "-p", "windows", "-p", "osx", "apt"])
+            .assert()
+            .success(),
+    );
+    assert!(stderr.contains(
+        "showing page from platform 'linux', because 'apt' does not exist in \
+        'windows', 'osx' and 'common'"
+    ));
+}
+
+#[test]
+fn translations() {
+    let config = local_mirror("translations");
+
+    tlrc_with_config(&config).arg("--update").assert().success();
+
+    let stderr = stderr_of(
+        &tlrc_with_config(&config)
+            .args(["--offline", "ls"])
+            .assert()
+            .success(),
+    );
+    assert!(stderr.contains("showing 'ls' in 'en' instead of 'de'"));
+
+    tlrc_with_config(&config)
+        .args(["--offline", "--quiet", "ls"])
+        .assert()
+        .success()
+        .stderr("");
+    tlrc_with_config(&config)
+        .args(["--offline", "tar"])
+        .assert()
+        .success()
+        .stderr("");
+
+    tlrc_with_config(&config)
+        .args(["--offline", "--list-translations", "tar"])
+        .assert()
+        .success()
+        .stdout("de     : common\nen     : common\n");
+    tlrc_with_config(&config)
+        .args(["--offline", "--list-translations", "apt"])
+        .assert()
+        .success()
+        .stdout("en     : linux\n");
+}
+
+#[test]
+fn languages_from_env() {
+    let config = local_mirror("languages_from_env");
+    let cfg = fs::read_to_string(&config).unwrap();
+    fs::write(&config, cfg.replace("languages = ['de', 'en']\n", "")).unwrap();
+
+    let tlrc_with_lang = |lang: &str| {
+        let mut cmd = tlrc_with_config(&config);
+        cmd.env("LANG", lang)
+            .env_remove("LANGUAGE")
+            .env_remove("LC_ALL")
+            .env_remove("LC_MESSAGES");
+        cmd
+    };
+
+    // Languages are taken from the environment if the config file does not set them.
+    tlrc_with_lang("de").arg("--update").assert().success();
+
+    let stderr = stderr_of(
+        &tlrc_with_lang("de")
+            .args(["--offline", "ls"])
+            .assert()
+            .success(),
+    );
+    assert!(stderr.contains("showing 'ls' in 'en' instead of 'de'"));
+
+    let stdout = stdout_of(
+        &tlrc_with_lang("de")
+            .args(["--offline", "--info"])
+            .assert()
+            .success(),
+    );
+    assert!(stdout.contains("de    : from LANG\nen    : English fallback\n"));
+
+    // 'de_DE' is not installed, so 'de' is the preferred language.
+    tlrc_with_lang("de_DE.UTF-8")
+        .args(["--offline", "tar"])
+        .assert()
+        .success()
+        .stderr("");
+
+    let stderr = stderr_of(
+        &tlrc_with_lang("de_DE.UTF-8")
+            .args(["--offline", "ls"])
+            .assert()
+            .success(),
+    );
+    assert!(stderr.contains("showing 'ls' in 'en' instead of 'de'"));
+}